@@ -0,0 +1,85 @@
+use crate::ipc::Command;
+use std::error::Error;
+
+pub const USAGE: &str = "\
+Usage: htpc_app_manager [OPTIONS] [COMMAND]
+
+Commands:
+  show            Bring the running launcher to the front
+  launch <APP>    Launch an app by name, starting the launcher if it isn't running
+  list            Print the apps as JSON
+  selection       Print the selected app as JSON
+  select <APP>    Select an app by name or index
+  reload          Reload the config files
+  children        Print the running apps as JSON
+  kill <APP>      Close a running app
+  status          Print the selection and running apps as JSON
+  quit            Close the launcher
+  press <ACTION>  Press up, down, left, right, activate, back, menu, page-left or page-right
+  home            Bring the launcher back over the app in front
+  import desktop  Add installed .desktop apps to apps.json, filtered by settings.json
+  import steam    Add installed Steam games to apps.json, filtered by settings.json
+  import flatpak  Add installed Flatpak apps to apps.json, hiding those in settings.json
+  import snap     Add installed snaps to apps.json, hiding those in settings.json
+
+Only one launcher runs at a time, starting another sends it COMMAND, or show
+if none is given. The same commands can be written one per line to the socket
+in $XDG_RUNTIME_DIR/htpc_app_manager.sock, each is answered with \"ok\",
+\"ok <json>\" or \"error <message>\".
+
+Launching an app that is already running brings its window back up with
+xdotool, which has to be installed and only works on X11.
+
+Steam games are handed to the Steam client, which returns straight away, so
+they don't show as running and the hooks in settings.json don't apply to them.
+
+Options:
+  --rows <N>    Rows of tiles per page, overrides settings.json
+  --cols <N>    Columns of tiles per page, overrides settings.json
+  -h, --help    Print this help";
+
+#[derive(Debug, Default, Clone)]
+pub struct Args {
+    pub rows: Option<usize>,
+    pub cols: Option<usize>,
+    pub help: bool,
+    pub command: Option<Command>,
+    pub import: Option<String>, // Source to merge into apps.json instead of starting
+}
+
+pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, Box<dyn Error>> {
+    let mut parsed = Args::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rows" => parsed.rows = Some(count(&arg, args.next())?),
+            "--cols" => parsed.cols = Some(count(&arg, args.next())?),
+            "-h" | "--help" => parsed.help = true,
+            "import" => match args.next().as_deref() {
+                Some(source @ ("desktop" | "steam" | "flatpak" | "snap")) => {
+                    parsed.import = Some(source.to_string())
+                }
+                Some(source) => return Err(format!("unknown source '{}'", source).into()),
+                None => return Err("import needs a source".into()),
+            },
+            _ if arg.starts_with('-') => {
+                return Err(format!("unexpected argument '{}'", arg).into());
+            }
+            // Everything after the command is its argument, so app names can have spaces
+            _ => {
+                let rest = args.by_ref().collect::<Vec<_>>().join(" ");
+                parsed.command = Some(Command::parse(&format!("{} {}", arg, rest))?);
+            }
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn count(flag: &str, value: Option<String>) -> Result<usize, Box<dyn Error>> {
+    let value = value.ok_or_else(|| format!("{} needs a value", flag))?;
+    match value.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("{} expects a positive number, got '{}'", flag, value).into()),
+    }
+}
@@ -0,0 +1,545 @@
+use crate::input::Binding;
+use gilrs::Button;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    fs,
+    os::unix::fs::PermissionsExt,
+};
+
+pub const CONFIG_DIR: &str = "~/.config/htpc_app_manager";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AppEntry {
+    pub name: String, // Also identifies the app's process in the supervisor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run: Option<String>, // Script run with bash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<LaunchCommand>, // or a program run directly
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub icon: String, // Empty for a tile with just the name, as imports leave it when none is found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>, // Names the shelf the app is on in the shelves layout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_launch: Option<String>, // Overrides the hook in settings.json, "" runs none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_exit: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LaunchCommand {
+    pub program: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub clear_env: bool, // Start from an empty environment instead of the launcher's
+}
+
+// Launcher options from settings.json, every field is optional
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub layout: Layout,
+    pub sort: SortMode,
+    pub continue_row: bool, // The last three apps launched, above the grid or as the first shelf
+    pub grid: GridSettings,
+    pub labels: LabelSettings,
+    pub input: InputSettings,
+    pub home: HomeSettings,
+    pub force_quit: ForceQuitSettings,
+    pub hooks: HookSettings,
+    pub remote: RemoteSettings,
+    pub mqtt: MqttSettings,
+    pub dbus: DbusSettings,
+    pub desktop: DesktopSettings,
+    pub steam: SteamSettings,
+    pub flatpak: DiscoverySettings,
+    pub snap: DiscoverySettings,
+}
+
+// Pages of tiles, or a row per category with grid.cols tiles and grid.rows shelves on screen
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    #[default]
+    Grid,
+    Shelves,
+}
+
+// Order of the tiles, recent and most used come from history.json
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortMode {
+    #[default]
+    Config,
+    Recent,
+    MostUsed,
+    Alphabetical,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct GridSettings {
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self { rows: 2, cols: 3 }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct InputSettings {
+    pub deadzone: f32,
+    pub repeat_delay_ms: u64, // Hold a direction this long before it repeats
+    pub repeat_interval_ms: u64, // then move once per interval
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.5,
+            repeat_delay_ms: 400,
+            repeat_interval_ms: 120,
+        }
+    }
+}
+
+// What happens to the foreground app when the home chord brings the launcher back
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HomeAction {
+    None,
+    Pause,
+    Close,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct HomeSettings {
+    pub chord: Vec<Binding>, // All held at once, e.g. ["Select", "Start"]
+    pub on_home: HomeAction,
+}
+
+impl Default for HomeSettings {
+    fn default() -> Self {
+        Self {
+            chord: vec![Binding::Button(Button::Mode)],
+            on_home: HomeAction::None,
+        }
+    }
+}
+
+// Holding the chord opens the close app overlay
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ForceQuitSettings {
+    pub chord: Vec<Binding>,
+    pub hold_ms: u64,
+}
+
+impl Default for ForceQuitSettings {
+    fn default() -> Self {
+        Self {
+            chord: vec![Binding::Button(Button::Select)],
+            hold_ms: 1500,
+        }
+    }
+}
+
+// The phone remote served over HTTP, read once at startup
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct RemoteSettings {
+    pub enabled: bool,
+    pub bind: String,
+    pub token: Option<String>, // Generated and kept in remote_token when not set
+}
+
+impl Default for RemoteSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "0.0.0.0:8080".to_string(),
+            token: None,
+        }
+    }
+}
+
+// Installed .desktop apps shown after the ones in apps.json
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DesktopSettings {
+    pub enabled: bool,
+    pub categories: Vec<String>, // Only apps in one of these, e.g. "AudioVideo", all if empty
+    pub exclude: Vec<String>,    // App names to leave out
+}
+
+// Installed Steam games, read straight from Steam's files. They are started through the
+// Steam client, so they never show as running and don't get the default hooks
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SteamSettings {
+    pub enabled: bool,
+    pub root: Option<String>,   // Found in the usual places when not set
+    pub launcher: Vec<String>,  // Run with the game's steam:// url added
+    pub libraries: Vec<String>, // Library folders to include, all if empty
+    pub games: Vec<String>,     // Appids or names to include, all games if empty
+    pub exclude: Vec<String>,   // Appids or names to leave out
+}
+
+impl Default for SteamSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            root: None,
+            launcher: vec!["steam".to_string()],
+            libraries: Vec::new(),
+            games: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+// Apps found from a package manager, matched on package id or app name
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DiscoverySettings {
+    pub enabled: bool,
+    pub hide: Vec<String>,
+    pub pin: Vec<String>, // Shown first, in this order
+}
+
+impl DiscoverySettings {
+    // Drops hidden apps and splits off the pinned ones in pin order, the rest by name
+    pub fn arrange(&self, found: Vec<(String, AppEntry)>) -> (Vec<AppEntry>, Vec<AppEntry>) {
+        let position = |list: &[String], id: &str, app: &AppEntry| {
+            list.iter().position(|key| key == id || *key == app.name)
+        };
+
+        let mut found: Vec<(Option<usize>, AppEntry)> = found
+            .into_iter()
+            .filter(|(id, app)| position(&self.hide, id, app).is_none())
+            .map(|(id, app)| (position(&self.pin, &id, &app), app))
+            .collect();
+        found.sort_by_key(|(pin, app)| (pin.unwrap_or(usize::MAX), app.name.to_lowercase()));
+
+        let (pinned, rest): (Vec<_>, Vec<_>) =
+            found.into_iter().partition(|(pin, _)| pin.is_some());
+        (
+            pinned.into_iter().map(|(_, app)| app).collect(),
+            rest.into_iter().map(|(_, app)| app).collect(),
+        )
+    }
+}
+
+// Methods and signals on the session bus, read once at startup
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DbusSettings {
+    pub enabled: bool,
+}
+
+// Home Assistant and friends over MQTT, read once at startup
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub topic_prefix: String,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "htpc_app_manager".to_string(),
+            username: None,
+            password: None,
+            topic_prefix: "htpc".to_string(),
+        }
+    }
+}
+
+// What a failing pre_launch hook does to the launch
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookFailure {
+    Abort,
+    Continue,
+}
+
+// Shell commands run around every app that doesn't set its own
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct HookSettings {
+    pub pre_launch: Option<String>,
+    pub post_exit: Option<String>,
+    pub on_failure: HookFailure,
+    pub timeout_secs: u64, // Hooks still running after this are killed and count as failed
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        Self {
+            pre_launch: None,
+            post_exit: None,
+            on_failure: HookFailure::Abort,
+            timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelPosition {
+    Below,
+    Overlay,
+    Hidden,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct LabelSettings {
+    pub position: LabelPosition,
+    pub focused_only: bool,
+    pub scale: f32, // Multiplies the font size, which already follows the tile size
+}
+
+impl Default for LabelSettings {
+    fn default() -> Self {
+        Self {
+            position: LabelPosition::Below,
+            focused_only: false,
+            scale: 1.0,
+        }
+    }
+}
+
+// A problem found with one entry of apps.json
+pub struct Diagnostic {
+    pub entry: String,
+    pub problem: String,
+    pub skipped: bool,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = if self.skipped { "skipped" } else { "warning" };
+        write!(f, "{} ({}): {}", self.entry, action, self.problem)
+    }
+}
+
+// Expanded path of a file in the config directory
+pub fn config_path(file: &str) -> String {
+    format!("{}/{}", shellexpand::tilde(CONFIG_DIR), file)
+}
+
+// Path of a file the launcher writes for itself, under $XDG_STATE_HOME
+pub fn state_path(file: &str) -> String {
+    let dir = dirs::state_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from(shellexpand::tilde("~/.local/state").as_ref()));
+    format!("{}/htpc_app_manager/{}", dir.display(), file)
+}
+
+// Reads apps.json, keeping every entry that is usable and reporting the rest
+pub fn load_from_json(path: &str) -> Result<(Vec<AppEntry>, Vec<Diagnostic>), Box<dyn Error>> {
+    let file = fs::read_to_string(path)?;
+    let raw: Vec<serde_json::Value> = serde_json::from_str(&file)?;
+
+    let mut apps = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut names = HashSet::new();
+
+    for (i, value) in raw.into_iter().enumerate() {
+        let entry: AppEntry = match serde_json::from_value(value) {
+            Ok(entry) => entry,
+            Err(e) => {
+                diagnostics.push(Diagnostic {
+                    entry: format!("entry {}", i + 1),
+                    problem: e.to_string(),
+                    skipped: true,
+                });
+                continue;
+            }
+        };
+
+        let mut skip = |problem: String| {
+            diagnostics.push(Diagnostic {
+                entry: entry.name.clone(),
+                problem,
+                skipped: true,
+            })
+        };
+
+        if !names.insert(entry.name.clone()) {
+            skip("duplicate name".to_string());
+            continue;
+        }
+        if let Err(e) = crate::launch::command_for(&entry) {
+            skip(e.to_string());
+            continue;
+        }
+
+        // A broken icon leaves an empty tile, the app can still be launched
+        if !entry.icon.is_empty()
+            && let Err(e) = check_icon(&shellexpand::tilde(&entry.icon))
+        {
+            diagnostics.push(Diagnostic {
+                entry: entry.name.clone(),
+                problem: e.to_string(),
+                skipped: false,
+            });
+        }
+
+        apps.push(entry);
+    }
+
+    Ok((apps, diagnostics))
+}
+
+// Pinned Flatpak and Snap apps, then apps.json, then the rest of what the enabled sources
+// find, apps.json wins on a name clash
+pub fn load_apps(
+    path: &str,
+    settings: &Settings,
+) -> Result<(Vec<AppEntry>, Vec<Diagnostic>), Box<dyn Error>> {
+    let (mut apps, diagnostics) = load_from_json(path)?;
+
+    let mut names: HashSet<String> = apps.iter().map(|entry| entry.name.clone()).collect();
+    let mut add = |apps: &mut Vec<AppEntry>, found: Vec<AppEntry>| {
+        for entry in found {
+            // Found apps aren't the user's to fix, ones that can't launch are left out quietly
+            if crate::launch::command_for(&entry).is_ok() && names.insert(entry.name.clone()) {
+                apps.push(entry);
+            }
+        }
+    };
+
+    let mut pinned = Vec::new();
+    let mut rest = Vec::new();
+    if settings.flatpak.enabled {
+        let (p, r) = crate::flatpak::scan(&settings.flatpak);
+        pinned.extend(p);
+        rest.extend(r);
+    }
+    if settings.snap.enabled {
+        let (p, r) = crate::snap::scan(&settings.snap);
+        pinned.extend(p);
+        rest.extend(r);
+    }
+
+    let mut front = Vec::new();
+    add(&mut front, pinned);
+    if settings.desktop.enabled {
+        add(&mut apps, desktop_apps(settings));
+    }
+    if settings.steam.enabled {
+        add(&mut apps, crate::steam::scan(&settings.steam));
+    }
+    add(&mut apps, rest);
+
+    front.extend(apps);
+    Ok((front, diagnostics))
+}
+
+// Installed .desktop apps, leaving out the ones the Flatpak and Snap sources own when
+// those are enabled so their hide and pin settings apply
+pub fn desktop_apps(settings: &Settings) -> Vec<AppEntry> {
+    let mut skip = Vec::new();
+    if settings.flatpak.enabled {
+        skip.extend(
+            crate::flatpak::exports()
+                .iter()
+                .map(|share| share.join("applications")),
+        );
+    }
+    if settings.snap.enabled {
+        skip.push(std::path::PathBuf::from(crate::snap::APPLICATIONS));
+    }
+
+    crate::desktop::scan(&settings.desktop, &skip)
+        .iter()
+        .filter_map(|entry| entry.to_app())
+        .collect()
+}
+
+// Appends found apps to apps.json, leaving existing entries untouched, and returns the added names
+pub fn merge_into_json(path: &str, found: Vec<AppEntry>) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut raw: Vec<serde_json::Value> = match fs::read_to_string(path) {
+        Ok(file) => serde_json::from_str(&file).map_err(|e| format!("{}: {}", path, e))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(format!("{}: {}", path, e).into()),
+    };
+
+    let mut names: HashSet<String> = raw
+        .iter()
+        .filter_map(|value| value["name"].as_str().map(str::to_string))
+        .collect();
+    let mut added = Vec::new();
+
+    for entry in found {
+        if crate::launch::command_for(&entry).is_ok() && names.insert(entry.name.clone()) {
+            added.push(entry.name.clone());
+            raw.push(serde_json::to_value(entry)?);
+        }
+    }
+
+    if !added.is_empty() {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&raw)? + "\n")?;
+    }
+
+    Ok(added)
+}
+
+// A missing settings.json just means the defaults
+pub fn load_settings(path: &str) -> Result<Settings, Box<dyn Error>> {
+    match fs::read_to_string(path) {
+        Ok(file) => Ok(serde_json::from_str(&file)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Scripts must exist and be executable before they are launched
+pub fn check_executable(path: &str) -> Result<(), Box<dyn Error>> {
+    let meta = fs::metadata(path).map_err(|e| format!("{}: {}", path, e))?;
+
+    if !meta.is_file() {
+        return Err(format!("{} is not a file", path).into());
+    }
+    if meta.permissions().mode() & 0o111 == 0 {
+        return Err(format!("{} is not executable", path).into());
+    }
+
+    Ok(())
+}
+
+// Only the header is decoded so startup stays fast, images that turn out broken when
+// the tile is drawn are reported then
+fn check_icon(path: &str) -> Result<(), Box<dyn Error>> {
+    image::io::Reader::open(path)
+        .map_err(|e| format!("{}: {}", path, e))?
+        .with_guessed_format()?
+        .into_dimensions()
+        .map_err(|e| format!("{}: {}", path, e))?;
+
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use crate::ipc::{Command, Remote};
+use zbus::{SignalContext, blocking::Connection, dbus_interface, fdo};
+
+const NAME: &str = "io.github.RoscoeEH.HtpcAppManager";
+const PATH: &str = "/io/github/RoscoeEH/HtpcAppManager";
+
+// The launcher's methods on the session bus, each handled on the UI thread
+struct Launcher {
+    remote: Remote,
+}
+
+impl Launcher {
+    fn call(&self, command: Command) -> fdo::Result<String> {
+        self.remote.call(command).map_err(fdo::Error::Failed)
+    }
+}
+
+#[dbus_interface(name = "io.github.RoscoeEH.HtpcAppManager")]
+impl Launcher {
+    fn launch(&self, name: String) -> fdo::Result<()> {
+        self.call(Command::Launch(name)).map(|_| ())
+    }
+
+    fn select(&self, index: u32) -> fdo::Result<()> {
+        self.call(Command::Select(index.to_string())).map(|_| ())
+    }
+
+    fn reload(&self) -> fdo::Result<()> {
+        self.call(Command::Reload).map(|_| ())
+    }
+
+    // Name and whether it is running, in grid order
+    fn list_apps(&self) -> fdo::Result<Vec<(String, bool)>> {
+        let list: serde_json::Value = serde_json::from_str(&self.call(Command::List)?)
+            .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+
+        Ok(list
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|app| {
+                (
+                    app["name"].as_str().unwrap_or_default().to_string(),
+                    app["running"].as_bool().unwrap_or_default(),
+                )
+            })
+            .collect())
+    }
+
+    fn raise(&self) -> fdo::Result<()> {
+        self.call(Command::Show).map(|_| ())
+    }
+
+    #[dbus_interface(signal)]
+    async fn app_started(ctxt: &SignalContext<'_>, name: &str, pid: u32) -> zbus::Result<()>;
+
+    // Code is -1 when the app was killed by a signal
+    #[dbus_interface(signal)]
+    async fn app_exited(ctxt: &SignalContext<'_>, name: &str, code: i32) -> zbus::Result<()>;
+}
+
+// Owns the well-known name on the session bus for as long as the launcher runs
+pub struct Service {
+    connection: Connection,
+}
+
+impl Service {
+    pub fn start(remote: Remote) -> zbus::Result<Self> {
+        Self::start_on(zbus::blocking::ConnectionBuilder::session()?, remote)
+    }
+
+    fn start_on(bus: zbus::blocking::ConnectionBuilder, remote: Remote) -> zbus::Result<Self> {
+        let connection = bus
+            .name(NAME)?
+            .serve_at(PATH, Launcher { remote })?
+            .build()?;
+
+        Ok(Self { connection })
+    }
+
+    pub fn app_started(&self, name: &str, pid: u32) {
+        self.emit(|ctxt| zbus::block_on(Launcher::app_started(ctxt, name, pid)));
+    }
+
+    pub fn app_exited(&self, name: &str, code: Option<i32>) {
+        let code = code.unwrap_or(-1);
+        self.emit(|ctxt| zbus::block_on(Launcher::app_exited(ctxt, name, code)));
+    }
+
+    fn emit(&self, send: impl FnOnce(&SignalContext<'static>) -> zbus::Result<()>) {
+        let result = self
+            .connection
+            .object_server()
+            .interface::<_, Launcher>(PATH)
+            .and_then(|iface| send(iface.signal_context()));
+        if let Err(e) = result {
+            eprintln!("Failed to send D-Bus signal: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::Inbox;
+    use eframe::egui;
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Stdio},
+        sync::mpsc,
+        thread,
+        time::Duration,
+    };
+
+    // A private bus, killed when dropped
+    struct Bus(Child);
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    // Needs dbus-daemon on PATH
+    #[test]
+    #[ignore]
+    fn answers_on_a_private_bus() {
+        let mut daemon = std::process::Command::new("dbus-daemon")
+            .args(["--session", "--print-address", "--nofork"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("dbus-daemon");
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        let _bus = Bus(daemon);
+        let address = address.trim();
+
+        let inbox = Inbox::new(&egui::Context::default(), Duration::from_secs(1));
+        let _service = Service::start_on(
+            zbus::blocking::ConnectionBuilder::address(address).unwrap(),
+            inbox.remote(),
+        )
+        .unwrap();
+
+        // Stand in for the UI thread
+        let (launched_tx, launched) = mpsc::channel();
+        thread::spawn(move || {
+            loop {
+                for request in inbox.requests() {
+                    let reply = match &request.command {
+                        Command::List => Ok(serde_json::json!([
+                            { "index": 0, "name": "Kodi", "running": true },
+                            { "index": 1, "name": "Jellyfin", "running": false },
+                        ])
+                        .to_string()),
+                        Command::Launch(name) if name == "Kodi" => {
+                            let _ = launched_tx.send(name.clone());
+                            Ok(String::new())
+                        }
+                        Command::Launch(name) => Err(format!("no app named {}", name)),
+                        _ => Ok(String::new()),
+                    };
+                    request.reply(reply);
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        let client = zbus::blocking::ConnectionBuilder::address(address)
+            .unwrap()
+            .build()
+            .unwrap();
+        let proxy = zbus::blocking::Proxy::new(&client, NAME, PATH, NAME).unwrap();
+
+        let apps: Vec<(String, bool)> = proxy.call("ListApps", &()).unwrap();
+        assert_eq!(
+            apps,
+            vec![("Kodi".to_string(), true), ("Jellyfin".to_string(), false)]
+        );
+
+        proxy.call::<_, _, ()>("Launch", &("Kodi",)).unwrap();
+        assert_eq!(
+            launched.recv_timeout(Duration::from_secs(1)).unwrap(),
+            "Kodi"
+        );
+        assert!(proxy.call::<_, _, ()>("Launch", &("Nope",)).is_err());
+    }
+}
@@ -0,0 +1,373 @@
+use crate::config::{AppEntry, DesktopSettings, LaunchCommand};
+use std::{
+    collections::{BTreeMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+// Largest first, the tile is drawn much bigger than any of these
+const ICON_SIZES: [&str; 9] = [
+    "512x512", "256x256", "192x192", "128x128", "96x96", "72x72", "64x64", "48x48", "32x32",
+];
+
+// Freedesktop main categories, the others are mostly toolkit and desktop tags like GTK or KDE
+const MAIN_CATEGORIES: [&str; 13] = [
+    "AudioVideo",
+    "Audio",
+    "Video",
+    "Development",
+    "Education",
+    "Game",
+    "Graphics",
+    "Network",
+    "Office",
+    "Science",
+    "Settings",
+    "System",
+    "Utility",
+];
+
+// The fields of a .desktop file the launcher cares about
+#[derive(Debug, Clone, Default)]
+pub struct DesktopEntry {
+    pub name: String,
+    pub exec: Vec<String>,
+    pub icon: Option<String>,
+    pub categories: Vec<String>,
+    pub no_display: bool,
+}
+
+impl DesktopEntry {
+    // The first main category listed, which names the app's shelf
+    pub fn category(&self) -> Option<String> {
+        self.categories
+            .iter()
+            .find(|c| MAIN_CATEGORIES.contains(&c.as_str()))
+            .cloned()
+    }
+
+    pub fn to_app(&self) -> Option<AppEntry> {
+        let (program, args) = self.exec.split_first()?;
+
+        Some(AppEntry {
+            name: self.name.clone(),
+            run: None,
+            command: Some(LaunchCommand {
+                program: program.clone(),
+                args: args.to_vec(),
+                cwd: None,
+                env: BTreeMap::new(),
+                clear_env: false,
+            }),
+            icon: self
+                .icon
+                .as_deref()
+                .and_then(|icon| find_icon(icon, &data_dirs()))
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            category: self.category(),
+            pre_launch: None,
+            post_exit: None,
+        })
+    }
+}
+
+// $XDG_DATA_HOME first so the user's own entries override the system's
+pub fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = dirs::data_dir().into_iter().collect();
+    let system = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    dirs.extend(env::split_paths(&system));
+    dirs
+}
+
+// Apps from every applications directory not in skip that pass the filters in settings
+pub fn scan(settings: &DesktopSettings, skip: &[PathBuf]) -> Vec<DesktopEntry> {
+    let dirs: Vec<PathBuf> = data_dirs()
+        .iter()
+        .map(|dir| dir.join("applications"))
+        .filter(|dir| !skip.contains(dir))
+        .collect();
+    scan_dirs(&dirs, settings)
+}
+
+fn scan_dirs(dirs: &[PathBuf], settings: &DesktopSettings) -> Vec<DesktopEntry> {
+    let mut seen_ids = HashSet::new();
+    let mut entries = Vec::new();
+
+    for dir in dirs {
+        for path in desktop_files(dir) {
+            // The same file id further down the list is shadowed
+            let id = path.strip_prefix(dir).unwrap_or(&path).to_path_buf();
+            if !seen_ids.insert(id) {
+                continue;
+            }
+
+            let Ok(file) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let Some(entry) = parse(&file) else {
+                continue;
+            };
+
+            let wanted = settings.categories.is_empty()
+                || entry
+                    .categories
+                    .iter()
+                    .any(|c| settings.categories.contains(c));
+            if !entry.no_display && wanted && !settings.exclude.contains(&entry.name) {
+                entries.push(entry);
+            }
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.name.to_lowercase());
+    entries
+}
+
+pub fn desktop_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(read) = fs::read_dir(dir) else {
+        return files;
+    };
+
+    for entry in read.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(desktop_files(&path));
+        } else if path.extension().is_some_and(|ext| ext == "desktop") {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    files
+}
+
+// None for anything that isn't a launchable application
+pub fn parse(file: &str) -> Option<DesktopEntry> {
+    let mut fields = BTreeMap::new();
+    let mut in_entry = false;
+
+    for line in file.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry || line.starts_with('#') {
+            continue;
+        }
+        // Localised keys like Name[de] are skipped, the plain one is used
+        if let Some((key, value)) = line.split_once('=')
+            && !key.contains('[')
+        {
+            fields.insert(key.trim(), unescape(value.trim()));
+        }
+    }
+
+    let is_true = |key: &str| fields.get(key).is_some_and(|value| value == "true");
+    if fields.get("Type").map(String::as_str) != Some("Application")
+        || is_true("Hidden")
+        || is_true("Terminal")
+    {
+        return None;
+    }
+
+    let exec = split_exec(fields.get("Exec")?);
+    if exec.is_empty() {
+        return None;
+    }
+
+    Some(DesktopEntry {
+        name: fields.get("Name")?.clone(),
+        exec,
+        icon: fields.get("Icon").filter(|icon| !icon.is_empty()).cloned(),
+        categories: fields
+            .get("Categories")
+            .map(|list| {
+                list.split(';')
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        no_display: is_true("NoDisplay"),
+    })
+}
+
+// Escapes allowed in any string value
+fn unescape(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+// Splits Exec into arguments, honouring double quotes, and drops the field codes
+// since nothing is ever opened with the app
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            '\\' if quoted => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            ' ' | '\t' if !quoted => {
+                if started {
+                    args.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            _ => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        args.push(current);
+    }
+
+    args.into_iter()
+        .filter_map(|arg| {
+            let stripped = strip_field_codes(&arg);
+            // An argument that was only a field code goes away entirely
+            if stripped.is_empty() && !arg.is_empty() {
+                None
+            } else {
+                Some(stripped)
+            }
+        })
+        .collect()
+}
+
+fn strip_field_codes(arg: &str) -> String {
+    let mut out = String::new();
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        if let Some('%') = chars.next() {
+            out.push('%');
+        }
+    }
+    out
+}
+
+// Icon names are looked up in the hicolor theme of each share dir, then pixmaps,
+// paths are used as given
+pub fn find_icon(icon: &str, share_dirs: &[PathBuf]) -> Option<PathBuf> {
+    if icon.starts_with('/') {
+        return Path::new(icon).is_file().then(|| PathBuf::from(icon));
+    }
+
+    let mut bases: Vec<PathBuf> = dirs::home_dir()
+        .map(|home| home.join(".icons"))
+        .into_iter()
+        .collect();
+    bases.extend(share_dirs.iter().map(|dir| dir.join("icons")));
+
+    for size in ICON_SIZES {
+        for base in &bases {
+            let path = base
+                .join("hicolor")
+                .join(size)
+                .join("apps")
+                .join(format!("{}.png", icon));
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+    }
+
+    ["/usr/share/pixmaps"]
+        .iter()
+        .flat_map(|dir| ["png", "jpg"].map(|ext| Path::new(dir).join(format!("{}.{}", icon, ext))))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_quoted_exec_and_drops_field_codes() {
+        assert_eq!(
+            split_exec(r#""/opt/My App/run" --title "say \"hi\"" %U"#),
+            vec!["/opt/My App/run", "--title", "say \"hi\""]
+        );
+        assert_eq!(
+            split_exec("player --volume=100%% %f --fullscreen"),
+            vec!["player", "--volume=100%", "--fullscreen"]
+        );
+        assert_eq!(split_exec(r#"app """#), vec!["app", ""]);
+    }
+
+    #[test]
+    fn strips_codes_inside_an_argument() {
+        assert_eq!(strip_field_codes("--file=%f"), "--file=");
+        assert_eq!(strip_field_codes("100%%"), "100%");
+        assert_eq!(strip_field_codes("%U"), "");
+    }
+
+    #[test]
+    fn parses_only_the_desktop_entry_group() {
+        let entry = parse(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=Media Player\n\
+             Name[de]=Medienspieler\n\
+             Exec=player %U\n\
+             Icon=player\n\
+             Categories=Qt;KDE;AudioVideo;\n\
+             \n\
+             [Desktop Action New]\n\
+             Name=New Window\n\
+             Exec=player --new\n",
+        )
+        .unwrap();
+
+        assert_eq!(entry.name, "Media Player");
+        assert_eq!(entry.exec, vec!["player"]);
+        assert_eq!(entry.category().as_deref(), Some("AudioVideo"));
+    }
+
+    #[test]
+    fn skips_entries_that_are_not_launchable_apps() {
+        let app = "[Desktop Entry]\nType=Application\nName=A\nExec=a\n";
+        assert!(parse(app).is_some());
+        assert!(parse(&format!("{}Hidden=true\n", app)).is_none());
+        assert!(parse(&format!("{}Terminal=true\n", app)).is_none());
+        assert!(parse(&app.replace("Application", "Link")).is_none());
+        assert!(parse(&app.replace("Exec=a", "Exec=%U")).is_none());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{
+    config::{AppEntry, DiscoverySettings, LaunchCommand},
+    desktop,
+};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+// The user installation first, like flatpak itself
+const INSTALLATIONS: [&str; 2] = ["~/.local/share/flatpak", "/var/lib/flatpak"];
+
+// Where each installation exports its .desktop files and icons
+pub fn exports() -> Vec<PathBuf> {
+    INSTALLATIONS
+        .iter()
+        .map(|dir| PathBuf::from(shellexpand::tilde(dir).as_ref()).join("exports/share"))
+        .collect()
+}
+
+// Installed Flatpak apps from the .desktop files they export, launched with flatpak run,
+// as the pinned ones and the rest
+pub fn scan(settings: &DiscoverySettings) -> (Vec<AppEntry>, Vec<AppEntry>) {
+    let shares = exports();
+    // Flatpak icons live in the exports, the system theme can still fill gaps
+    let icon_dirs: Vec<PathBuf> = shares.iter().cloned().chain(desktop::data_dirs()).collect();
+
+    let mut found = Vec::new();
+    for share in &shares {
+        for path in desktop::desktop_files(&share.join("applications")) {
+            // Exports are named after the app id, e.g. tv.kodi.Kodi.desktop
+            let Some(id) = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+            else {
+                continue;
+            };
+            if found.iter().any(|(seen, _)| *seen == id) {
+                continue;
+            }
+            let Some(entry) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|file| desktop::parse(&file))
+                .filter(|entry| !entry.no_display)
+            else {
+                continue;
+            };
+
+            let category = entry.category();
+            let app = AppEntry {
+                name: entry.name,
+                run: None,
+                command: Some(LaunchCommand {
+                    program: "flatpak".to_string(),
+                    args: vec!["run".to_string(), id.clone()],
+                    cwd: None,
+                    env: BTreeMap::new(),
+                    clear_env: false,
+                }),
+                icon: entry
+                    .icon
+                    .and_then(|icon| desktop::find_icon(&icon, &icon_dirs))
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                category,
+                pre_launch: None,
+                post_exit: None,
+            };
+            found.push((id, app));
+        }
+    }
+
+    settings.arrange(found)
+}
@@ -0,0 +1,126 @@
+// Shape of one page of the app grid, apps that don't fit continue on the next page
+#[derive(Debug, Clone, Copy)]
+pub struct Grid {
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl Grid {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            rows: rows.max(1),
+            cols: cols.max(1),
+        }
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.rows * self.cols
+    }
+
+    pub fn page_of(&self, idx: usize) -> usize {
+        idx / self.page_size()
+    }
+
+    pub fn pages(&self, len: usize) -> usize {
+        len.div_ceil(self.page_size()).max(1)
+    }
+
+    // The last column steps onto the same row of the next page
+    pub fn right(&self, idx: usize, len: usize) -> usize {
+        if !(idx + 1).is_multiple_of(self.cols) {
+            if idx + 1 < len { idx + 1 } else { idx }
+        } else if self.page_of(idx) + 1 < self.pages(len) {
+            (idx + self.page_size() + 1 - self.cols).min(len - 1)
+        } else {
+            idx
+        }
+    }
+
+    // The first column steps onto the same row of the previous page
+    pub fn left(&self, idx: usize) -> usize {
+        if !idx.is_multiple_of(self.cols) {
+            idx - 1
+        } else if self.page_of(idx) > 0 {
+            idx + self.cols - 1 - self.page_size()
+        } else {
+            idx
+        }
+    }
+
+    // Rows run on across pages, a short last row catches the selection
+    pub fn down(&self, idx: usize, len: usize) -> usize {
+        let next = idx + self.cols;
+        let next_row = (idx / self.cols + 1) * self.cols;
+
+        if next < len {
+            next
+        } else if next_row < len {
+            len - 1
+        } else {
+            idx
+        }
+    }
+
+    pub fn up(&self, idx: usize) -> usize {
+        idx.checked_sub(self.cols).unwrap_or(idx)
+    }
+
+    pub fn next_page(&self, idx: usize, len: usize) -> usize {
+        if self.page_of(idx) + 1 < self.pages(len) {
+            (idx + self.page_size()).min(len - 1)
+        } else {
+            idx
+        }
+    }
+
+    pub fn prev_page(&self, idx: usize) -> usize {
+        idx.checked_sub(self.page_size()).unwrap_or(idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two pages of 2x3, the second holding only 6 and 7
+    const LEN: usize = 8;
+
+    #[test]
+    fn right_wraps_onto_the_next_page() {
+        let grid = Grid::new(2, 3);
+        assert_eq!(grid.right(0, LEN), 1);
+        assert_eq!(grid.right(2, LEN), 6);
+        // The same row is missing on the short last page, the last app catches it
+        assert_eq!(grid.right(5, LEN), 7);
+        assert_eq!(grid.right(7, LEN), 7);
+    }
+
+    #[test]
+    fn left_wraps_onto_the_previous_page() {
+        let grid = Grid::new(2, 3);
+        assert_eq!(grid.left(7), 6);
+        assert_eq!(grid.left(6), 2);
+        assert_eq!(grid.left(3), 3);
+        assert_eq!(grid.left(0), 0);
+    }
+
+    #[test]
+    fn pages_clamp_to_the_short_last_page() {
+        let grid = Grid::new(2, 3);
+        assert_eq!(grid.pages(LEN), 2);
+        assert_eq!(grid.pages(0), 1);
+        assert_eq!(grid.next_page(1, LEN), 7);
+        assert_eq!(grid.next_page(7, LEN), 7);
+        assert_eq!(grid.prev_page(7), 1);
+        assert_eq!(grid.prev_page(4), 4);
+    }
+
+    #[test]
+    fn down_stops_on_a_short_last_row() {
+        let grid = Grid::new(2, 3);
+        assert_eq!(grid.down(4, LEN), 7);
+        assert_eq!(grid.down(5, LEN), 7);
+        assert_eq!(grid.down(7, LEN), 7);
+        assert_eq!(grid.up(7), 4);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::config::{AppEntry, SortMode};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Usage {
+    pub count: u64,
+    pub last: u64, // Unix time of the latest launch
+}
+
+// Launch counts and times per app name, kept in history.json in the state directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct History {
+    apps: BTreeMap<String, Usage>,
+}
+
+impl History {
+    // A missing history.json just means nothing has been launched yet
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(file) => Ok(serde_json::from_str(&file)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, name: &str) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let usage = self.apps.entry(name.to_string()).or_default();
+        usage.count += 1;
+        usage.last = now;
+    }
+
+    fn usage(&self, name: &str) -> Usage {
+        self.apps.get(name).cloned().unwrap_or_default()
+    }
+
+    // Stable, so apps that tie stay in config order
+    pub fn sort(&self, apps: &mut [AppEntry], mode: SortMode) {
+        match mode {
+            SortMode::Config => {}
+            SortMode::Recent => apps.sort_by_key(|app| Reverse(self.usage(&app.name).last)),
+            SortMode::MostUsed => apps.sort_by_key(|app| {
+                let usage = self.usage(&app.name);
+                Reverse((usage.count, usage.last))
+            }),
+            SortMode::Alphabetical => apps.sort_by_key(|app| app.name.to_lowercase()),
+        }
+    }
+
+    // Indexes of the apps launched most recently, newest first
+    pub fn recent(&self, apps: &[AppEntry], count: usize) -> Vec<usize> {
+        let mut launched: Vec<(u64, usize)> = apps
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, app)| self.apps.get(&app.name).map(|usage| (usage.last, idx)))
+            .collect();
+        launched.sort_by_key(|(last, _)| Reverse(*last));
+
+        launched
+            .into_iter()
+            .take(count)
+            .map(|(_, idx)| idx)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(name: &str) -> AppEntry {
+        AppEntry {
+            name: name.to_string(),
+            run: None,
+            command: None,
+            icon: String::new(),
+            category: None,
+            pre_launch: None,
+            post_exit: None,
+        }
+    }
+
+    // In config order: Kodi, jellyfin, Steam, Plex, Celeste
+    fn apps() -> Vec<AppEntry> {
+        ["Kodi", "jellyfin", "Steam", "Plex", "Celeste"]
+            .map(app)
+            .to_vec()
+    }
+
+    // Kodi and Steam were used as often, Steam more recently, Plex most of all,
+    // Celeste never
+    fn history() -> History {
+        let usage = |count, last| Usage { count, last };
+        History {
+            apps: BTreeMap::from([
+                ("Kodi".to_string(), usage(3, 100)),
+                ("jellyfin".to_string(), usage(1, 400)),
+                ("Steam".to_string(), usage(3, 200)),
+                ("Plex".to_string(), usage(9, 300)),
+                ("Removed".to_string(), usage(20, 500)),
+            ]),
+        }
+    }
+
+    fn sorted(mode: SortMode) -> Vec<String> {
+        let mut apps = apps();
+        history().sort(&mut apps, mode);
+        apps.into_iter().map(|app| app.name).collect()
+    }
+
+    #[test]
+    fn sorts_by_each_mode() {
+        assert_eq!(
+            sorted(SortMode::Config),
+            ["Kodi", "jellyfin", "Steam", "Plex", "Celeste"]
+        );
+        assert_eq!(
+            sorted(SortMode::Recent),
+            ["jellyfin", "Plex", "Steam", "Kodi", "Celeste"]
+        );
+        assert_eq!(
+            sorted(SortMode::MostUsed),
+            ["Plex", "Steam", "Kodi", "jellyfin", "Celeste"]
+        );
+        assert_eq!(
+            sorted(SortMode::Alphabetical),
+            ["Celeste", "jellyfin", "Kodi", "Plex", "Steam"]
+        );
+    }
+
+    #[test]
+    fn ties_keep_config_order() {
+        let mut apps = apps();
+        History::default().sort(&mut apps, SortMode::Recent);
+        assert_eq!(apps, self::apps());
+
+        History::default().sort(&mut apps, SortMode::MostUsed);
+        assert_eq!(apps, self::apps());
+    }
+
+    #[test]
+    fn recent_is_newest_first_and_truncated() {
+        let apps = apps();
+        assert_eq!(history().recent(&apps, 3), vec![1, 3, 2]);
+        assert_eq!(history().recent(&apps, 10), vec![1, 3, 2, 0]);
+        assert_eq!(history().recent(&apps, 0), Vec::<usize>::new());
+        assert_eq!(History::default().recent(&apps, 3), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn record_counts_and_round_trips_through_a_file() {
+        let mut history = History::default();
+        history.record("Kodi");
+        history.record("Kodi");
+        assert_eq!(history.usage("Kodi").count, 2);
+        assert!(history.usage("Kodi").last > 0);
+
+        let path =
+            std::env::temp_dir().join(format!("htpc_history_{}/history.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        history.save(path).unwrap();
+        let loaded = History::load(path).unwrap();
+        let _ = fs::remove_dir_all(Path::new(path).parent().unwrap());
+
+        assert_eq!(loaded.usage("Kodi").count, 2);
+        assert_eq!(loaded.usage("Kodi").last, history.usage("Kodi").last);
+        assert_eq!(History::load(path).unwrap().usage("Kodi").count, 0);
+    }
+}
@@ -0,0 +1,380 @@
+use gilrs::{Axis, Button, EventType, Gamepad, GamepadId};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+// How far a stick has to move before the rebinding screen captures it
+const CAPTURE_THRESHOLD: f32 = 0.7;
+
+// The rebinding screen waits this long for a button before keeping the old binding
+pub const REBIND_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Holding Menu this long on the rebinding screen cancels it
+pub const REBIND_CANCEL_HOLD: Duration = Duration::from_secs(2);
+
+// Logical actions the launcher understands, independent of the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Activate,
+    Back,
+    Menu,
+    PageLeft,
+    PageRight,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Activate,
+        Action::Back,
+        Action::Menu,
+        Action::PageLeft,
+        Action::PageRight,
+        Action::Quit,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Activate => "Activate",
+            Action::Back => "Back",
+            Action::Menu => "Menu",
+            Action::PageLeft => "Page left",
+            Action::PageRight => "Page right",
+            Action::Quit => "Quit",
+        }
+    }
+
+    // Held directions keep moving the selection
+    fn repeats(self) -> bool {
+        matches!(
+            self,
+            Action::Up
+                | Action::Down
+                | Action::Left
+                | Action::Right
+                | Action::PageLeft
+                | Action::PageRight
+        )
+    }
+}
+
+pub type Actions = HashSet<Action>;
+
+// A gilrs button, or one direction of an axis written as "LeftStickY+" or "LeftStickY-"
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Button(Button),
+    Axis(Axis, bool),
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        // Reuse gilrs' own names for buttons and axes
+        let axis = |name: &str| serde_json::from_value::<Axis>(name.into());
+
+        if let Some(name) = s.strip_suffix('+') {
+            axis(name).map(|a| Binding::Axis(a, true))
+        } else if let Some(name) = s.strip_suffix('-') {
+            axis(name).map(|a| Binding::Axis(a, false))
+        } else {
+            serde_json::from_value::<Button>(s.as_str().into()).map(Binding::Button)
+        }
+        .map_err(|_| format!("unknown button or axis '{}'", s))
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        let name = |value: serde_json::Value| value.as_str().unwrap_or_default().to_string();
+
+        match binding {
+            Binding::Button(button) => name(serde_json::json!(button)),
+            Binding::Axis(axis, positive) => {
+                format!(
+                    "{}{}",
+                    name(serde_json::json!(axis)),
+                    if positive { '+' } else { '-' }
+                )
+            }
+        }
+    }
+}
+
+impl Binding {
+    // Axes count as pressed once they leave the deadzone
+    fn is_pressed(self, gamepad: &Gamepad, deadzone: f32) -> bool {
+        match self {
+            Binding::Button(button) => gamepad.is_pressed(button),
+            Binding::Axis(axis, true) => gamepad.value(axis) > deadzone,
+            Binding::Axis(axis, false) => gamepad.value(axis) < -deadzone,
+        }
+    }
+}
+
+pub type Bindings = BTreeMap<Action, Vec<Binding>>;
+
+// Contents of bindings.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    // Replaces the built-in binding for each action listed
+    pub default: Bindings,
+    // Keyed on gamepad name or uuid, replaces only the actions listed
+    pub controllers: BTreeMap<String, Bindings>,
+}
+
+impl InputConfig {
+    // A missing bindings.json just means the built-in bindings
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(file) => Ok(serde_json::from_str(&file)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    // Built-in bindings, then the default table, then the pad's own overrides
+    pub fn bindings_for(&self, gamepad: &Gamepad) -> Bindings {
+        let mut bindings = builtin();
+        bindings.extend(self.default.clone());
+
+        for key in [uuid_string(gamepad), gamepad.name().to_string()] {
+            if let Some(overrides) = self.controllers.get(&key) {
+                bindings.extend(overrides.clone());
+            }
+        }
+
+        bindings
+    }
+}
+
+fn builtin() -> Bindings {
+    use Binding::{Axis as A, Button as B};
+
+    BTreeMap::from([
+        (
+            Action::Up,
+            vec![B(Button::DPadUp), A(Axis::LeftStickY, true)],
+        ),
+        (
+            Action::Down,
+            vec![B(Button::DPadDown), A(Axis::LeftStickY, false)],
+        ),
+        (
+            Action::Left,
+            vec![B(Button::DPadLeft), A(Axis::LeftStickX, false)],
+        ),
+        (
+            Action::Right,
+            vec![B(Button::DPadRight), A(Axis::LeftStickX, true)],
+        ),
+        (Action::Activate, vec![B(Button::South)]),
+        (Action::Back, vec![B(Button::East)]),
+        (Action::Menu, vec![B(Button::Start)]),
+        (Action::PageLeft, vec![B(Button::LeftTrigger)]),
+        (Action::PageRight, vec![B(Button::RightTrigger)]),
+        (Action::Quit, vec![]),
+    ])
+}
+
+pub fn uuid_string(gamepad: &Gamepad) -> String {
+    gamepad
+        .uuid()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// Actions whose bindings are held on the pad right now
+pub fn held(gamepad: &Gamepad, bindings: &Bindings, deadzone: f32) -> Actions {
+    bindings
+        .iter()
+        .filter(|(_, list)| list.iter().any(|b| b.is_pressed(gamepad, deadzone)))
+        .map(|(action, _)| *action)
+        .collect()
+}
+
+// True while every binding in the chord is held on the pad
+pub fn chord_held(gamepad: &Gamepad, chord: &[Binding], deadzone: f32) -> bool {
+    !chord.is_empty() && chord.iter().all(|b| b.is_pressed(gamepad, deadzone))
+}
+
+// Actions triggered by a button press event
+pub fn pressed(button: Button, bindings: &Bindings) -> Actions {
+    bindings
+        .iter()
+        .filter(|(_, list)| list.contains(&Binding::Button(button)))
+        .map(|(action, _)| *action)
+        .collect()
+}
+
+struct Held {
+    since: Instant,
+    last_fired: Instant,
+}
+
+// Turns held actions into presses, each action on its own edge, repeating directions
+#[derive(Default)]
+pub struct Repeater {
+    held: HashMap<Action, Held>,
+}
+
+impl Repeater {
+    pub fn update(&mut self, held: &Actions, delay: Duration, interval: Duration) -> Actions {
+        let now = Instant::now();
+        let mut fired = Actions::new();
+
+        self.held.retain(|action, _| held.contains(action));
+
+        for action in held {
+            match self.held.get_mut(action) {
+                None => {
+                    self.held.insert(
+                        *action,
+                        Held {
+                            since: now,
+                            last_fired: now,
+                        },
+                    );
+                    fired.insert(*action);
+                }
+                Some(state)
+                    if action.repeats()
+                        && now - state.since >= delay
+                        && now - state.last_fired >= interval =>
+                {
+                    state.last_fired = now;
+                    fired.insert(*action);
+                }
+                Some(_) => {}
+            }
+        }
+
+        fired
+    }
+}
+
+// Walks through every action asking for a button on one controller
+pub struct Rebinder {
+    pub step: usize,
+    pub step_started: Instant,
+    pub controller: Option<(GamepadId, String)>,
+    pub bindings: Bindings,
+    // Axes that have to return to centre before they can be captured again
+    tripped: HashSet<Axis>,
+    // When Menu started being held, it has to be let go once after opening the screen
+    menu_since: Option<Instant>,
+    menu_released: bool,
+}
+
+impl Rebinder {
+    pub fn new() -> Self {
+        Self {
+            step: 0,
+            step_started: Instant::now(),
+            controller: None,
+            bindings: Bindings::new(),
+            tripped: HashSet::new(),
+            menu_since: None,
+            menu_released: false,
+        }
+    }
+
+    pub fn current(&self) -> Option<Action> {
+        Action::ALL.get(self.step).copied()
+    }
+
+    // Binds the current action to the next button or stick push from the first pad used
+    pub fn feed(&mut self, id: GamepadId, name: &str, event: &EventType) {
+        if let Some((locked, _)) = &self.controller
+            && *locked != id
+        {
+            return;
+        }
+
+        let binding = match *event {
+            EventType::ButtonPressed(button, _) if button != Button::Unknown => {
+                Binding::Button(button)
+            }
+            EventType::AxisChanged(axis, value, _) if axis != Axis::Unknown => {
+                if value.abs() < CAPTURE_THRESHOLD / 2.0 {
+                    self.tripped.remove(&axis);
+                    return;
+                }
+                if value.abs() < CAPTURE_THRESHOLD || !self.tripped.insert(axis) {
+                    return;
+                }
+                Binding::Axis(axis, value > 0.0)
+            }
+            _ => return,
+        };
+
+        let Some(action) = self.current() else {
+            return;
+        };
+        self.controller
+            .get_or_insert_with(|| (id, name.to_string()));
+        self.bindings.insert(action, vec![binding]);
+        self.next();
+    }
+
+    // Keeps the existing binding for an action nobody pressed anything for
+    pub fn tick(&mut self) {
+        if self.current().is_some() && self.step_started.elapsed() > REBIND_TIMEOUT {
+            self.next();
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.current().is_none()
+    }
+
+    // Tracks Menu under the old bindings so the pad can back out of the screen
+    pub fn hold_menu(&mut self, held: bool) {
+        if !held {
+            self.menu_released = true;
+            self.menu_since = None;
+        } else if self.menu_released {
+            self.menu_since.get_or_insert_with(Instant::now);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.menu_since
+            .is_some_and(|since| since.elapsed() >= REBIND_CANCEL_HOLD)
+    }
+
+    fn next(&mut self) {
+        self.step += 1;
+        self.step_started = Instant::now();
+    }
+}
@@ -0,0 +1,362 @@
+use crate::input::Action;
+use eframe::egui;
+use std::{
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    os::{
+        fd::AsRawFd,
+        unix::net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+const LOCK_FILE: &str = "htpc_app_manager.lock";
+const SOCKET_FILE: &str = "htpc_app_manager.sock";
+
+// Added to the hook timeout so a launch can start once its pre_launch hook has finished
+const REPLY_MARGIN: Duration = Duration::from_secs(5);
+
+// Something another process asks the running launcher to do, one line on the socket.
+// Queries answer with JSON after the "ok"
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Show,
+    Launch(String),
+    List,
+    Selection,
+    Select(String), // Index into the app list, or an app name
+    Reload,
+    Children,
+    Kill(String),
+    Status,
+    Quit,
+    Press(Action), // As if pressed on a gamepad
+    Home,          // As if the home chord was pressed
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let (verb, arg) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let arg = arg.trim().to_string();
+        let needs_arg = |command: fn(String) -> Command, what: &str| {
+            if arg.is_empty() {
+                Err(format!("{} needs {}", verb, what))
+            } else {
+                Ok(command(arg.clone()))
+            }
+        };
+
+        match verb {
+            "show" => Ok(Command::Show),
+            "launch" => needs_arg(Command::Launch, "an app name"),
+            "list" => Ok(Command::List),
+            "selection" => Ok(Command::Selection),
+            "select" => needs_arg(Command::Select, "an index or app name"),
+            "reload" => Ok(Command::Reload),
+            "children" => Ok(Command::Children),
+            "kill" => needs_arg(Command::Kill, "an app name"),
+            "status" => Ok(Command::Status),
+            "quit" => Ok(Command::Quit),
+            "press" => serde_json::from_value(arg.as_str().into())
+                .map(Command::Press)
+                .map_err(|_| format!("unknown action '{}'", arg)),
+            "home" => Ok(Command::Home),
+            _ => Err(format!("unknown command '{}'", verb)),
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        match self {
+            Command::Show => "show".to_string(),
+            Command::Launch(name) => format!("launch {}", name),
+            Command::List => "list".to_string(),
+            Command::Selection => "selection".to_string(),
+            Command::Select(target) => format!("select {}", target),
+            Command::Reload => "reload".to_string(),
+            Command::Children => "children".to_string(),
+            Command::Kill(name) => format!("kill {}", name),
+            Command::Status => "status".to_string(),
+            Command::Quit => "quit".to_string(),
+            Command::Press(action) => format!(
+                "press {}",
+                serde_json::json!(action).as_str().unwrap_or_default()
+            ),
+            Command::Home => "home".to_string(),
+        }
+    }
+
+    // Commands that make sense to start the launcher for when it isn't running
+    pub fn starts_launcher(&self) -> bool {
+        matches!(self, Command::Show | Command::Launch(_))
+    }
+}
+
+// A command waiting for the UI thread, which answers through reply
+pub struct Request {
+    pub command: Command,
+    reply: Sender<Result<String, String>>,
+}
+
+impl Request {
+    pub fn reply(self, result: Result<String, String>) {
+        // The client may have hung up already
+        let _ = self.reply.send(result);
+    }
+}
+
+// Commands from every remote source queue up here for the UI thread
+pub struct Inbox {
+    rx: Receiver<Request>,
+    remote: Remote,
+}
+
+impl Inbox {
+    pub fn new(ctx: &egui::Context, hook_timeout: Duration) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let inbox = Self {
+            rx,
+            remote: Remote {
+                tx,
+                ctx: ctx.clone(),
+                timeout_ms: Arc::new(AtomicU64::new(0)),
+            },
+        };
+        inbox.set_hook_timeout(hook_timeout);
+        inbox
+    }
+
+    // Remotes wait as long as a launch can take, which follows hooks.timeout_secs
+    pub fn set_hook_timeout(&self, hook_timeout: Duration) {
+        let timeout = reply_timeout(hook_timeout).as_millis() as u64;
+        self.remote.timeout_ms.store(timeout, Ordering::Relaxed);
+    }
+
+    // Handed to each server so it can pass commands on
+    pub fn remote(&self) -> Remote {
+        self.remote.clone()
+    }
+
+    // Commands received since the last call
+    pub fn requests(&self) -> Vec<Request> {
+        self.rx.try_iter().collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct Remote {
+    tx: Sender<Request>,
+    ctx: egui::Context,
+    timeout_ms: Arc<AtomicU64>,
+}
+
+impl Remote {
+    // Runs a command on the UI thread and waits for its answer
+    pub fn call(&self, command: Command) -> Result<String, String> {
+        let (reply, rx) = mpsc::channel();
+        self.tx
+            .send(Request { command, reply })
+            .map_err(|_| "the launcher is shutting down".to_string())?;
+        // The UI may be idle in the background, wake it to handle the command
+        self.ctx.request_repaint();
+        let timeout = Duration::from_millis(self.timeout_ms.load(Ordering::Relaxed));
+        rx.recv_timeout(timeout)
+            .unwrap_or_else(|_| Err("the launcher did not answer".to_string()))
+    }
+}
+
+// How long to wait for the launcher to answer, given the hook timeout in settings
+pub fn reply_timeout(hook_timeout: Duration) -> Duration {
+    hook_timeout + REPLY_MARGIN
+}
+
+// $XDG_RUNTIME_DIR is per user and cleared on logout, so stale files don't outlive the session
+fn runtime_path(file: &str) -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(file)
+}
+
+// Held by the one running launcher, the kernel drops it when the process exits
+pub struct Lock {
+    _file: File,
+}
+
+// None if another launcher already holds the lock
+pub fn lock() -> io::Result<Option<Lock>> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(runtime_path(LOCK_FILE))?;
+
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == -1 {
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() == Some(libc::EWOULDBLOCK) {
+            return Ok(None);
+        }
+        return Err(e);
+    }
+
+    Ok(Some(Lock { _file: file }))
+}
+
+// Accepts commands on the socket and hands them to the UI thread
+pub struct Server {
+    _lock: Lock,
+    path: PathBuf,
+}
+
+impl Server {
+    pub fn start(lock: Lock, remote: Remote) -> io::Result<Self> {
+        let path = runtime_path(SOCKET_FILE);
+        // Only the lock holder gets here, so a socket already there is left over from a crash
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let remote = remote.clone();
+                        thread::spawn(move || serve(stream, remote));
+                    }
+                    Err(e) => eprintln!("Control socket error: {}", e),
+                }
+            }
+        });
+
+        Ok(Self { _lock: lock, path })
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Answers each line with "ok", "ok <reply>" or "error <message>"
+fn serve(stream: UnixStream, remote: Remote) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+
+    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = Command::parse(&line).and_then(|command| remote.call(command));
+
+        let answer = match result {
+            Ok(reply) if reply.is_empty() => "ok".to_string(),
+            Ok(reply) => format!("ok {}", reply),
+            Err(e) => format!("error {}", e),
+        };
+        if writeln!(writer, "{}", answer).is_err() {
+            return;
+        }
+    }
+}
+
+// Sends one command to the running launcher and returns its reply
+pub fn send(command: &Command, timeout: Duration) -> Result<String, Box<dyn Error>> {
+    let path = runtime_path(SOCKET_FILE);
+    let mut stream = UnixStream::connect(&path).map_err(|e| {
+        format!(
+            "a launcher is running but {} can't be reached: {}",
+            path.display(),
+            e
+        )
+    })?;
+    stream.set_read_timeout(Some(timeout))?;
+    writeln!(stream, "{}", command.to_line())?;
+
+    let mut answer = String::new();
+    BufReader::new(stream).read_line(&mut answer)?;
+    let answer = answer.trim_end();
+
+    if let Some(message) = answer.strip_prefix("error") {
+        Err(message.trim_start().into())
+    } else if let Some(reply) = answer.strip_prefix("ok") {
+        Ok(reply.trim_start().to_string())
+    } else {
+        Err("no answer from the running launcher".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_command_round_trips() {
+        let mut commands = vec![
+            Command::Show,
+            Command::Launch("Big Picture".to_string()),
+            Command::List,
+            Command::Selection,
+            Command::Select("3".to_string()),
+            Command::Reload,
+            Command::Children,
+            Command::Kill("Kodi".to_string()),
+            Command::Status,
+            Command::Quit,
+            Command::Home,
+        ];
+        commands.extend(Action::ALL.map(Command::Press));
+
+        for command in commands {
+            assert_eq!(Command::parse(&command.to_line()), Ok(command));
+        }
+    }
+
+    #[test]
+    fn press_uses_the_action_names() {
+        assert_eq!(
+            Command::parse("press page-right"),
+            Ok(Command::Press(Action::PageRight))
+        );
+        assert_eq!(
+            Command::Press(Action::PageLeft).to_line(),
+            "press page-left"
+        );
+        assert!(Command::parse("press PageRight").is_err());
+        assert!(Command::parse("press jump").is_err());
+    }
+
+    #[test]
+    fn commands_that_need_an_argument_say_so() {
+        for verb in ["launch", "select", "kill"] {
+            assert!(Command::parse(verb).is_err());
+            assert!(Command::parse(&format!("{}   ", verb)).is_err());
+        }
+        assert_eq!(
+            Command::parse("launch"),
+            Err("launch needs an app name".to_string())
+        );
+        assert!(Command::parse("press").is_err());
+    }
+
+    #[test]
+    fn parsing_trims_and_rejects_unknown_verbs() {
+        assert_eq!(
+            Command::parse("  launch   Kodi  \n"),
+            Ok(Command::Launch("Kodi".to_string()))
+        );
+        assert_eq!(Command::parse("status\n"), Ok(Command::Status));
+        assert_eq!(
+            Command::parse("jump"),
+            Err("unknown command 'jump'".to_string())
+        );
+        assert!(Command::parse("").is_err());
+    }
+}
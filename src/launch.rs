@@ -0,0 +1,271 @@
+use crate::config::{self, AppEntry, LaunchCommand};
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    io::{BufRead, BufReader, Read},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+// Builds the process for an entry: bash running its script, or its program run directly
+pub fn command_for(entry: &AppEntry) -> Result<Command, Box<dyn Error>> {
+    match (&entry.run, &entry.command) {
+        (Some(run), None) => {
+            let script_path = shellexpand::tilde(run).to_string();
+            config::check_executable(&script_path)?;
+
+            let mut cmd = Command::new("bash");
+            cmd.arg(script_path);
+            Ok(cmd)
+        }
+        (None, Some(spec)) => build(spec),
+        (Some(_), Some(_)) => Err("has both run and command, only one is allowed".into()),
+        (None, None) => Err("needs either run or command".into()),
+    }
+}
+
+fn build(spec: &LaunchCommand) -> Result<Command, Box<dyn Error>> {
+    // Env values are expanded against the launcher's environment,
+    // the program, args and cwd see the entry's env as well
+    let mut vars = BTreeMap::new();
+    for (key, value) in &spec.env {
+        vars.insert(key.clone(), expand(value, &BTreeMap::new())?);
+    }
+
+    let program = expand(&spec.program, &vars)?;
+    let program =
+        find_program(&program).ok_or_else(|| format!("{} was not found in PATH", program))?;
+
+    let mut cmd = Command::new(program);
+    for arg in &spec.args {
+        cmd.arg(expand(arg, &vars)?);
+    }
+    if spec.clear_env {
+        cmd.env_clear();
+    }
+    cmd.envs(&vars);
+    if let Some(cwd) = &spec.cwd {
+        cmd.current_dir(expand(cwd, &vars)?);
+    }
+
+    Ok(cmd)
+}
+
+// Tilde and $VAR expansion, an unset variable is an error rather than an empty string
+fn expand(s: &str, vars: &BTreeMap<String, String>) -> Result<String, Box<dyn Error>> {
+    shellexpand::full_with_context(
+        s,
+        || dirs::home_dir().map(|home| home.to_string_lossy().into_owned()),
+        |var| match vars.get(var) {
+            Some(value) => Ok(Some(value.clone())),
+            None => env::var(var).map(Some),
+        },
+    )
+    .map(|expanded| expanded.into_owned())
+    .map_err(|e| format!("{}: {}", s, e).into())
+}
+
+// Resolves the program the way a shell would, paths are used as given
+fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        return config::check_executable(program)
+            .ok()
+            .map(|_| PathBuf::from(program));
+    }
+
+    env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|path| is_executable(path))
+    })
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| config::check_executable(path).is_ok())
+}
+
+// The app's own hook wins over the default from settings.json, an empty one disables it
+pub fn hook<'a>(own: &'a Option<String>, default: &'a Option<String>) -> Option<&'a str> {
+    own.as_ref()
+        .or(default.as_ref())
+        .map(|cmd| cmd.trim())
+        .filter(|cmd| !cmd.is_empty())
+}
+
+// Runs a hook with sh and waits for it, logging its output under the app's name
+pub fn run_hook(
+    app: &str,
+    stage: &str,
+    cmd: &str,
+    timeout: Duration,
+) -> Result<(), Box<dyn Error>> {
+    println!("[{} {}] {}", app, stage, cmd);
+
+    let mut child = Command::new("sh")
+        .args(["-c", cmd])
+        .process_group(0)
+        .env("HTPC_APP_NAME", app)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{}: {}", cmd, e))?;
+
+    let prefix = format!("[{} {}]", app, stage);
+    let (tx, rx) = mpsc::channel();
+    if let Some(pipe) = child.stdout.take() {
+        log_lines(prefix.clone(), pipe, tx.clone());
+    }
+    if let Some(pipe) = child.stderr.take() {
+        log_lines(prefix, pipe, tx);
+    }
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            // Anything the hook started in the background goes with it
+            unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+            let _ = child.wait();
+            return Err(format!("{} timed out after {:?}", cmd, timeout).into());
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    // The last line of output usually says what went wrong. Something the hook left
+    // running in the background can hold the pipes open, so don't wait on them for long
+    let mut last = None;
+    for _ in 0..2 {
+        match rx.recv_timeout(Duration::from_millis(500)) {
+            Ok(Some(line)) => last = Some(line),
+            Ok(None) => {}
+            Err(_) => break,
+        }
+    }
+
+    if status.success() {
+        Ok(())
+    } else {
+        match last {
+            Some(line) => Err(format!("{} exited with {}: {}", cmd, status, line).into()),
+            None => Err(format!("{} exited with {}", cmd, status).into()),
+        }
+    }
+}
+
+// Prints each line of a pipe, sending the last one once it closes
+fn log_lines(prefix: String, pipe: impl Read + Send + 'static, last: mpsc::Sender<Option<String>>) {
+    thread::spawn(move || {
+        let mut line_seen = None;
+        for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+            println!("{} {}", prefix, line);
+            line_seen = Some(line);
+        }
+        let _ = last.send(line_seen);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(program: &str, args: &[&str], env: &[(&str, &str)]) -> LaunchCommand {
+        LaunchCommand {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            cwd: None,
+            env: env
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            clear_env: false,
+        }
+    }
+
+    fn output(spec: &LaunchCommand) -> String {
+        let out = build(spec).unwrap().output().unwrap();
+        String::from_utf8(out.stdout).unwrap().trim().to_string()
+    }
+
+    #[test]
+    fn expands_tilde_and_variables() {
+        let home = dirs::home_dir().unwrap().to_string_lossy().into_owned();
+        let vars = BTreeMap::from([("GAME".to_string(), "celeste".to_string())]);
+
+        assert_eq!(
+            expand("~/games/$GAME", &vars).unwrap(),
+            format!("{}/games/celeste", home)
+        );
+        assert_eq!(
+            expand("${GAME}.sh", &vars).unwrap(),
+            "celeste.sh".to_string()
+        );
+        assert_eq!(
+            expand("$PATH", &BTreeMap::new()).unwrap(),
+            env::var("PATH").unwrap()
+        );
+    }
+
+    #[test]
+    fn an_unset_variable_is_an_error() {
+        let e = expand("$HTPC_TEST_UNSET_VARIABLE/bin", &BTreeMap::new()).unwrap_err();
+        assert!(e.to_string().starts_with("$HTPC_TEST_UNSET_VARIABLE/bin: "));
+
+        let unset = spec("sh", &[], &[("DIR", "$HTPC_TEST_UNSET_VARIABLE")]);
+        assert!(build(&unset).is_err());
+    }
+
+    #[test]
+    fn env_values_are_seen_by_args_and_the_program() {
+        let home = env::var("HOME").unwrap();
+        let greet = spec(
+            "printf",
+            &["%s", "$GREETING"],
+            &[("GREETING", "hello from $HOME")],
+        );
+        assert_eq!(output(&greet), format!("hello from {}", home));
+
+        let show = spec("env", &[], &[("GREETING", "hello from $HOME")]);
+        assert!(
+            output(&show)
+                .lines()
+                .any(|line| line == format!("GREETING=hello from {}", home))
+        );
+    }
+
+    #[test]
+    fn clear_env_starts_from_only_the_entry_env() {
+        let mut show = spec("env", &[], &[("GREETING", "hi")]);
+        assert!(output(&show).lines().any(|line| line.starts_with("PATH=")));
+
+        show.clear_env = true;
+        assert_eq!(output(&show), "GREETING=hi");
+    }
+
+    #[test]
+    fn programs_are_found_in_path_or_rejected() {
+        assert!(find_program("sh").is_some_and(|path| path.is_absolute()));
+        assert!(find_program("htpc-test-no-such-program").is_none());
+        assert!(build(&spec("htpc-test-no-such-program", &[], &[])).is_err());
+    }
+
+    #[test]
+    fn own_hook_wins_and_empty_disables_the_default() {
+        let default = Some("pactl set-default-sink hdmi".to_string());
+        assert_eq!(hook(&None, &default), default.as_deref());
+        assert_eq!(
+            hook(&Some(" xrandr ".to_string()), &default),
+            Some("xrandr")
+        );
+        assert_eq!(hook(&Some(String::new()), &default), None);
+        assert_eq!(hook(&None, &None), None);
+    }
+}
@@ -1,44 +1,175 @@
+mod cli;
+mod config;
+mod dbus;
+mod desktop;
+mod flatpak;
+mod grid;
+mod history;
+mod input;
+mod ipc;
+mod launch;
+mod mqtt;
+mod shelves;
+mod snap;
+mod steam;
+mod supervisor;
+mod textures;
+mod toast;
+mod watcher;
+mod web;
+
+use config::{
+    AppEntry, Diagnostic, HomeAction, HookFailure, LabelPosition, Layout, Settings, SortMode,
+};
 use eframe::egui;
-use gilrs::{Button, EventType, Gilrs};
-use serde::Deserialize;
-use std::{error::Error, fs, process::Command};
-
-#[derive(Debug, Deserialize, Clone)]
-struct AppEntry {
-    #[allow(dead_code)]
-    name: String, // Name for interacting with json, not used
-    run: String,
-    icon: String,
-}
+use gilrs::{EventType, Gilrs};
+use grid::Grid;
+use history::History;
+use input::{Action, Actions, InputConfig, Rebinder, Repeater};
+use shelves::Shelves;
+use std::{
+    error::Error,
+    path::Path,
+    process::Command,
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+use supervisor::Supervisor;
+use textures::{Slot, TextureCache};
+use toast::Toasts;
+use watcher::ConfigWatcher;
 
-type AppConfig = Vec<AppEntry>;
+// Apps that fail within this long of launching are reported as launch failures
+const EARLY_EXIT: Duration = Duration::from_secs(5);
 
-const GRID_ROWS: usize = 2;
-const GRID_COLS: usize = 3;
+// Editors write in several steps, wait for them to finish before reloading
+const RELOAD_DELAY: Duration = Duration::from_millis(300);
+
+// An app waiting on its pre_launch hook, which runs on its own thread
+struct Pending {
+    name: String,
+    cmd: Command,
+    hook: Receiver<Result<(), String>>,
+    replies: Vec<ipc::Request>, // Remote launches answered once the app has started
+}
 
 struct HtpcApp {
     apps: Vec<AppEntry>,
     selected: usize,
+    settings: Settings,
+    args: cli::Args,
+    grid: Grid,
+    shelves: Shelves,
+    history: History,
+    recent: Vec<usize>, // The Continue row, empty when it is turned off
+    // Tile focused in the Continue row above the grid, and where to go back to below it
+    continue_col: Option<usize>,
+    grid_selected: usize,
     bg_texture: Option<egui::TextureHandle>,
     animation_start: Option<std::time::Instant>,
     animation_idx: Option<usize>,
     gilrs: Gilrs,
-    last_any_pressed: bool,
+    input: InputConfig,
+    rebinder: Option<Rebinder>,
+    repeater: Repeater,
+    home_held: bool,
+    force_quit_since: Option<(Instant, bool)>,
+    force_quit: Option<usize>,
+    supervisor: Supervisor,
+    launching: Vec<Pending>,
+    toasts: Toasts,
+    diagnostics: Vec<Diagnostic>,
+    show_diagnostics: bool,
+    watcher: Option<ConfigWatcher>,
+    reload_at: Option<Instant>,
+    textures: TextureCache,
+    show_debug: bool,
+    inbox: ipc::Inbox,
+    _server: Option<ipc::Server>,
+    remote_actions: Actions,
+    remote_url: Option<String>,
+    dbus: Option<dbus::Service>,
 }
 
 impl HtpcApp {
-    fn load_from_json(path: &str) -> Result<Vec<AppEntry>, Box<dyn Error>> {
-        let file = fs::read_to_string(path)?;
-        let apps: AppConfig = serde_json::from_str(&file)?;
-        Ok(apps)
-    }
+    fn new(
+        ctx: &egui::Context,
+        args: cli::Args,
+        lock: Option<ipc::Lock>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut diagnostics = Vec::new();
 
-    fn new() -> Result<Self, Box<dyn Error>> {
-        let apps = Self::load_from_json(
-            shellexpand::tilde("~/.config/htpc_app_manager/apps.json")
-                .to_string()
-                .as_str(),
-        )?;
+        // Read first since it says which sources add apps
+        let settings_path = config::config_path("settings.json");
+        let settings = config::load_settings(&settings_path).unwrap_or_else(|e| {
+            diagnostics.push(Diagnostic {
+                entry: settings_path,
+                problem: e.to_string(),
+                skipped: true,
+            });
+            Settings::default()
+        });
+
+        // A broken config still brings up the launcher so the problem can be shown
+        let path = config::config_path("apps.json");
+        let apps = match config::load_apps(&path, &settings) {
+            Ok((apps, problems)) => {
+                diagnostics.extend(problems);
+                apps
+            }
+            Err(e) => {
+                diagnostics.push(Diagnostic {
+                    entry: path,
+                    problem: e.to_string(),
+                    skipped: true,
+                });
+                Vec::new()
+            }
+        };
+
+        let bindings_path = config::config_path("bindings.json");
+        let input = InputConfig::load(&bindings_path).unwrap_or_else(|e| {
+            diagnostics.push(Diagnostic {
+                entry: bindings_path,
+                problem: e.to_string(),
+                skipped: true,
+            });
+            InputConfig::default()
+        });
+
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+
+        let watcher = ConfigWatcher::new(Path::new(&*shellexpand::tilde(config::CONFIG_DIR)))
+            .map_err(|e| eprintln!("Not watching config for changes: {}", e))
+            .ok();
+
+        let inbox = ipc::Inbox::new(ctx, Duration::from_secs(settings.hooks.timeout_secs));
+        let server = lock.and_then(|lock| {
+            ipc::Server::start(lock, inbox.remote())
+                .map_err(|e| eprintln!("Not listening for commands: {}", e))
+                .ok()
+        });
+
+        // Only the instance holding the lock serves the phone remote, MQTT and D-Bus
+        let remote_url = if settings.remote.enabled && server.is_some() {
+            web::start(&settings.remote, inbox.remote())
+                .map_err(|e| eprintln!("Phone remote not started: {}", e))
+                .ok()
+        } else {
+            None
+        };
+        if settings.mqtt.enabled && server.is_some() {
+            mqtt::start(&settings.mqtt, inbox.remote());
+        }
+        let dbus = if settings.dbus.enabled && server.is_some() {
+            dbus::Service::start(inbox.remote())
+                .map_err(|e| eprintln!("Not on D-Bus: {}", e))
+                .ok()
+        } else {
+            None
+        };
 
         let gilrs = Gilrs::new().unwrap();
 
@@ -47,75 +178,861 @@ impl HtpcApp {
             println!("Found gamepad: {}", gamepad.name());
         }
 
-        Ok(Self {
+        let grid = grid_for(&settings, &args);
+        let history_path = config::state_path("history.json");
+        let history = History::load(&history_path).unwrap_or_else(|e| {
+            eprintln!("Not using launch history from {}: {}", history_path, e);
+            History::default()
+        });
+
+        let mut toasts = Toasts::default();
+        if let Some(url) = &remote_url {
+            toasts.notify(format!("Phone remote at {}", url));
+        }
+
+        let mut app = Self {
             apps,
             selected: 0,
+            settings,
+            args,
+            grid,
+            shelves: Shelves::new(&[], Vec::new()),
+            history,
+            recent: Vec::new(),
+            continue_col: None,
+            grid_selected: 0,
             bg_texture: None,
             animation_start: None,
             animation_idx: None,
-            gilrs: Gilrs::new().unwrap(),
-            last_any_pressed: false,
-        })
+            gilrs,
+            input,
+            rebinder: None,
+            repeater: Repeater::default(),
+            home_held: false,
+            force_quit_since: None,
+            force_quit: None,
+            supervisor: Supervisor::new(),
+            launching: Vec::new(),
+            toasts,
+            show_diagnostics: !diagnostics.is_empty(),
+            diagnostics,
+            watcher,
+            reload_at: None,
+            textures: TextureCache::new(ctx),
+            show_debug: false,
+            inbox,
+            _server: server,
+            remote_actions: Actions::new(),
+            remote_url,
+            dbus,
+        };
+
+        // Start on whatever was launched last
+        app.arrange();
+        if let Some(&latest) = app.history.recent(&app.apps, 1).first() {
+            app.selected = latest;
+        }
+        Ok(app)
+    }
+
+    // Swaps in freshly loaded config files, keeping the old ones if they can't be read
+    fn reload(&mut self) {
+        match config::load_settings(&config::config_path("settings.json")) {
+            Ok(settings) => {
+                self.grid = grid_for(&settings, &self.args);
+                self.inbox
+                    .set_hook_timeout(Duration::from_secs(settings.hooks.timeout_secs));
+                self.settings = settings;
+            }
+            Err(e) => self.toasts.push(
+                "settings.json was not reloaded, keeping the previous settings",
+                vec![e.to_string()],
+            ),
+        }
+
+        match InputConfig::load(&config::config_path("bindings.json")) {
+            Ok(input) => self.input = input,
+            Err(e) => self.toasts.push(
+                "bindings.json was not reloaded, keeping the previous bindings",
+                vec![e.to_string()],
+            ),
+        }
+
+        let (apps, diagnostics) =
+            match config::load_apps(&config::config_path("apps.json"), &self.settings) {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    self.toasts.push(
+                        "apps.json was not reloaded, keeping the previous config",
+                        vec![e.to_string()],
+                    );
+                    return;
+                }
+            };
+
+        for entry in &apps {
+            match self.apps.iter().find(|old| old.name == entry.name) {
+                None => println!("Added {}", entry.name),
+                Some(old) if old != entry => println!("Changed {}", entry.name),
+                Some(_) => {}
+            }
+        }
+        for old in &self.apps {
+            if !apps.iter().any(|entry| entry.name == old.name) {
+                println!("Removed {}", old.name);
+            }
+        }
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+
+        // Stay on the same app if it still exists
+        let selected_name = self.apps.get(self.selected).map(|entry| entry.name.clone());
+        self.selected = selected_name
+            .and_then(|name| apps.iter().position(|entry| entry.name == name))
+            .unwrap_or(0);
+
+        let mut paths: Vec<String> = apps.iter().map(|entry| entry.icon.clone()).collect();
+        paths.push(config::config_path("background.jpg"));
+        self.textures.retain(&paths);
+
+        self.apps = apps;
+        self.show_diagnostics = !diagnostics.is_empty();
+        self.diagnostics = diagnostics;
+        self.animation_idx = None;
+        self.animation_start = None;
+        self.continue_col = None;
+        self.arrange();
+    }
+
+    // Puts the apps in the configured order and rebuilds the shelves, the selection,
+    // launch flash and each shelf's focus stay on the same apps
+    fn arrange(&mut self) {
+        let focus = self.shelves.focus(&self.apps);
+        let name_at = |idx: Option<usize>| {
+            idx.and_then(|idx| self.apps.get(idx))
+                .map(|app| app.name.clone())
+        };
+        let selected = name_at(Some(self.selected));
+        let flashing = name_at(self.animation_idx);
+        let grid_selected = name_at(Some(self.grid_selected));
+
+        self.history.sort(&mut self.apps, self.settings.sort);
+
+        let position = |name: Option<String>| {
+            name.and_then(|name| self.apps.iter().position(|app| app.name == name))
+        };
+        self.selected = position(selected).unwrap_or(0);
+        self.animation_idx = position(flashing);
+        self.grid_selected = position(grid_selected).unwrap_or(0);
+
+        self.recent = if self.settings.continue_row {
+            self.history.recent(&self.apps, 3)
+        } else {
+            Vec::new()
+        };
+        self.shelves = Shelves::new(&self.apps, self.recent.clone());
+        self.shelves.refocus(&self.apps, &focus);
+
+        // Follow the selected app along the Continue row, or drop back to the grid
+        if self.continue_col.is_some() {
+            self.continue_col = self.recent.iter().position(|idx| *idx == self.selected);
+            if self.continue_col.is_none() {
+                self.selected = self.grid_selected;
+            }
+        }
     }
 
-    fn launch(&self, idx: usize) -> Result<(), Box<dyn Error>> {
-        if let Some(entry) = self.apps.get(idx) {
-            let script_path = shellexpand::tilde(&entry.run).to_string();
-            let _ = Command::new("bash").arg(script_path).spawn()?;
+    // The Continue row sits above the pages in the grid layout
+    fn continue_strip(&self) -> &[usize] {
+        if self.settings.layout == Layout::Grid {
+            &self.recent[..self.recent.len().min(self.grid.cols)]
+        } else {
+            &[]
         }
+    }
+
+    // Starts the app, or its pre_launch hook first without holding up the UI
+    fn launch(&mut self, idx: usize) -> Result<(), Box<dyn Error>> {
+        let Some(entry) = self.apps.get(idx) else {
+            return Ok(());
+        };
+        let name = entry.name.clone();
 
+        // Bring an already running app back up instead of starting a second copy
+        if self.supervisor.is_running(&name) {
+            self.supervisor.focus(&name);
+            return Ok(());
+        }
+        if self.launching.iter().any(|pending| pending.name == name) {
+            return Ok(());
+        }
+
+        let cmd = launch::command_for(entry)?;
+
+        let hooks = &self.settings.hooks;
+        let Some(hook) = launch::hook(&entry.pre_launch, &hooks.pre_launch) else {
+            return self.start(&name, cmd, false);
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let (app, hook, timeout) = (
+            name.clone(),
+            hook.to_string(),
+            Duration::from_secs(hooks.timeout_secs),
+        );
+        std::thread::spawn(move || {
+            let result = launch::run_hook(&app, "pre_launch", &hook, timeout);
+            let _ = tx.send(result.map_err(|e| e.to_string()));
+        });
+
+        self.toasts.notify(format!("Starting {}", name));
+        self.launching.push(Pending {
+            name,
+            cmd,
+            hook: rx,
+            replies: Vec::new(),
+        });
         Ok(())
     }
-    fn gamepad_actions(&mut self) -> (bool, bool, bool, bool, bool) {
-        let mut left = false;
-        let mut right = false;
-        let mut up = false;
-        let mut down = false;
-        let mut activate = false;
 
-        self.gilrs.inc();
+    // Starts the apps whose pre_launch hook has finished
+    fn poll_launching(&mut self) {
+        let mut i = 0;
+        while i < self.launching.len() {
+            let hook = match self.launching[i].hook.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => {
+                    i += 1;
+                    continue;
+                }
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    Err("the hook thread stopped unexpectedly".to_string())
+                }
+            };
+            let pending = self.launching.remove(i);
 
-        // Event queue
-        while let Some(ev) = self.gilrs.next_event() {
-            match ev.event {
-                EventType::ButtonPressed(Button::DPadLeft, _) => left = true,
-                EventType::ButtonPressed(Button::DPadRight, _) => right = true,
-                EventType::ButtonPressed(Button::DPadUp, _) => up = true,
-                EventType::ButtonPressed(Button::DPadDown, _) => down = true,
-                EventType::ButtonPressed(Button::South, _) => activate = true,
-                _ => {}
+            let result = match hook {
+                Err(e) if self.settings.hooks.on_failure == HookFailure::Abort => {
+                    Err(format!("pre_launch hook failed, {}", e).into())
+                }
+                Err(e) => {
+                    eprintln!("{} pre_launch hook failed: {}", pending.name, e);
+                    self.start(&pending.name, pending.cmd, true)
+                }
+                Ok(()) => self.start(&pending.name, pending.cmd, true),
+            };
+
+            let result = result.map(|_| String::new()).map_err(|e| e.to_string());
+            if let Err(e) = &result {
+                self.toasts.push(
+                    format!("Failed to launch {}", pending.name),
+                    vec![e.clone()],
+                );
+            }
+            for request in pending.replies {
+                request.reply(result.clone());
             }
         }
+    }
 
-        // Polling state
-        for (_id, gamepad) in self.gilrs.gamepads() {
-            if gamepad.is_pressed(Button::DPadLeft) {
-                left = true;
+    // Spawns the app and records the launch, the post_exit hook undoes a pre_launch
+    // hook that ran for an app which then failed to start
+    fn start(&mut self, name: &str, cmd: Command, hooked: bool) -> Result<(), Box<dyn Error>> {
+        let pid = match self.supervisor.spawn(name, cmd) {
+            Ok(pid) => pid,
+            Err(e) => {
+                if hooked {
+                    self.run_post_exit(name);
+                }
+                return Err(e);
             }
-            if gamepad.is_pressed(Button::DPadRight) {
-                right = true;
+        };
+        println!("Launched {} (pid {})", name, pid);
+        if let Some(dbus) = &self.dbus {
+            dbus.app_started(name, pid);
+        }
+
+        self.history.record(name);
+        if let Err(e) = self.history.save(&config::state_path("history.json")) {
+            eprintln!("Failed to save launch history: {}", e);
+        }
+        // Launching only moves tiles around when they follow the history
+        if self.settings.sort != SortMode::Config || self.settings.continue_row {
+            self.arrange();
+        }
+
+        Ok(())
+    }
+
+    // Carries out a command from the command line or the control socket
+    fn handle(
+        &mut self,
+        command: &ipc::Command,
+        frame: &mut eframe::Frame,
+    ) -> Result<String, String> {
+        match command {
+            ipc::Command::Show => {
+                raise(frame);
+                Ok(String::new())
             }
-            if gamepad.is_pressed(Button::DPadUp) {
-                up = true;
+            ipc::Command::Launch(name) => {
+                let idx = self.find_app(name)?;
+                self.selected = idx;
+                self.continue_col = None;
+                self.launch(idx).map_err(|e| e.to_string())?;
+                Ok(String::new())
             }
-            if gamepad.is_pressed(Button::DPadDown) {
-                down = true;
+            ipc::Command::List => {
+                let apps: Vec<_> = self
+                    .apps
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, entry)| {
+                        serde_json::json!({
+                            "index": idx,
+                            "name": entry.name,
+                            "icon": shellexpand::tilde(&entry.icon),
+                            "running": self.supervisor.is_running(&entry.name),
+                        })
+                    })
+                    .collect();
+                Ok(serde_json::Value::from(apps).to_string())
             }
-            if gamepad.is_pressed(Button::South) {
-                activate = true;
+            ipc::Command::Selection => Ok(self.selection_json().to_string()),
+            ipc::Command::Select(target) => {
+                self.selected = match target.parse::<usize>() {
+                    Ok(idx) if idx < self.apps.len() => idx,
+                    Ok(idx) => return Err(format!("no app at index {}", idx)),
+                    Err(_) => self.find_app(target)?,
+                };
+                self.continue_col = None;
+                Ok(String::new())
+            }
+            ipc::Command::Reload => {
+                self.reload();
+                Ok(String::new())
+            }
+            ipc::Command::Children => Ok(self.children_json().to_string()),
+            ipc::Command::Kill(name) => {
+                let name = self
+                    .supervisor
+                    .running()
+                    .map(|sup| sup.name.clone())
+                    .find(|running| running.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| format!("{} is not running", name))?;
+                self.supervisor
+                    .terminate(&name)
+                    .map_err(|e| e.to_string())?;
+                self.toasts.notify(format!("Closing {}", name));
+                Ok(String::new())
+            }
+            ipc::Command::Status => Ok(serde_json::json!({
+                "selected": self.selection_json(),
+                "foreground": self.supervisor.foreground().map(|sup| &sup.name),
+                "children": self.children_json(),
+            })
+            .to_string()),
+            ipc::Command::Quit => {
+                frame.close();
+                Ok(String::new())
+            }
+            // Taken with the gamepad and keyboard input this frame
+            ipc::Command::Press(action) => {
+                self.remote_actions.insert(*action);
+                Ok(String::new())
             }
+            ipc::Command::Home => {
+                if !frame.info().window_info.focused {
+                    self.go_home(frame);
+                }
+                Ok(String::new())
+            }
+        }
+    }
+
+    // App names are matched ignoring case so scripts can write "kodi"
+    fn find_app(&self, name: &str) -> Result<usize, String> {
+        self.apps
+            .iter()
+            .position(|entry| entry.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("no app named {}", name))
+    }
+
+    fn selection_json(&self) -> serde_json::Value {
+        match self.apps.get(self.selected) {
+            Some(entry) => serde_json::json!({ "index": self.selected, "name": entry.name }),
+            None => serde_json::Value::Null,
         }
+    }
 
-        // Debounce by frame
-        let any_pressed = left || right || up || down || activate;
-        let trigger = any_pressed && !self.last_any_pressed;
+    fn children_json(&self) -> serde_json::Value {
+        self.supervisor
+            .running()
+            .map(|sup| {
+                serde_json::json!({
+                    "name": sup.name,
+                    "pid": sup.pid,
+                    "uptime_secs": sup.started.elapsed().as_secs(),
+                    "paused": sup.paused,
+                })
+            })
+            .collect()
+    }
 
-        self.last_any_pressed = any_pressed;
+    // One app tile with its icon, label, running dot and launch flash. The same app can
+    // be on screen twice, so focused says whether this is the tile with the focus
+    fn draw_tile(&mut self, ui: &egui::Ui, rect: egui::Rect, idx: usize, focused: bool) {
+        let ctx = ui.ctx();
+        let app = &self.apps[idx];
 
-        if trigger {
-            (left, right, up, down, activate)
+        // Draw background
+        let bg_color = if focused {
+            ui.visuals().selection.bg_fill
         } else {
-            (false, false, false, false, false)
+            ui.visuals().faint_bg_color
+        };
+        ui.painter().rect_filled(rect, 12.0, bg_color);
+
+        // Flash animation on press
+        if focused
+            && Some(idx) == self.animation_idx
+            && let Some(start) = self.animation_start
+        {
+            let elapsed = start.elapsed().as_secs_f32();
+            let duration = 0.25;
+
+            if elapsed < duration {
+                // Flash overlay
+                let alpha = (1.0 - (elapsed / duration)).clamp(0.0, 1.0);
+                let flash =
+                    egui::Color32::from_rgba_unmultiplied(255, 255, 255, (200.0 * alpha) as u8);
+                ui.painter().rect_filled(rect, 12.0, flash);
+
+                ctx.request_repaint(); // Animate
+            } else {
+                self.animation_idx = None;
+                self.animation_start = None;
+            }
+        }
+
+        // Name label, sized to the tile
+        let labels = &self.settings.labels;
+        let font_size = rect.height() * 0.08 * labels.scale;
+        let show_label =
+            labels.position != LabelPosition::Hidden && (!labels.focused_only || focused);
+        let caption_height = if labels.position == LabelPosition::Below && show_label {
+            font_size * 1.6
+        } else {
+            0.0
+        };
+
+        // Draw icon
+        let padding = rect.width() * 0.10;
+
+        let icon_rect = egui::Rect::from_min_max(
+            rect.min + egui::vec2(padding, padding),
+            rect.max - egui::vec2(padding, padding + caption_height),
+        );
+
+        match self
+            .textures
+            .get(&app.icon, icon_rect.size() * ctx.pixels_per_point())
+        {
+            Slot::Ready(texture) => {
+                ui.painter().image(
+                    texture.id(),
+                    icon_rect,
+                    egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                    egui::Color32::WHITE,
+                );
+            }
+            // Pulse a placeholder until the icon is decoded
+            Slot::Loading => {
+                let t = ctx.input(|i| i.time) as f32;
+                let alpha = 20.0 + 20.0 * (t * 3.0).sin().abs();
+                ui.painter().rect_filled(
+                    icon_rect,
+                    12.0,
+                    egui::Color32::from_white_alpha(alpha as u8),
+                );
+            }
+            Slot::Failed => {}
+        }
+
+        if show_label {
+            let label_rect = if labels.position == LabelPosition::Below {
+                egui::Rect::from_min_max(
+                    egui::pos2(icon_rect.min.x, icon_rect.max.y),
+                    egui::pos2(icon_rect.max.x, rect.max.y - padding / 2.0),
+                )
+            } else {
+                egui::Rect::from_min_max(
+                    egui::pos2(icon_rect.min.x, icon_rect.max.y - font_size * 1.6),
+                    icon_rect.max,
+                )
+            };
+            draw_label(ui.painter(), label_rect, &app.name, font_size);
+        }
+
+        // Running indicator, amber while paused
+        if let Some(sup) = self.supervisor.get(&app.name) {
+            let radius = rect.width() * 0.03;
+            let color = if sup.paused {
+                egui::Color32::from_rgb(240, 180, 60)
+            } else {
+                egui::Color32::from_rgb(80, 220, 100)
+            };
+            ui.painter().circle_filled(
+                rect.right_top() + egui::vec2(-radius * 2.0, radius * 2.0),
+                radius,
+                color,
+            );
+        }
+    }
+
+    // A header and a strip of tiles per category, scrolled to keep the focus on screen
+    fn draw_shelves(
+        &mut self,
+        ui: &egui::Ui,
+        tile_size: egui::Vec2,
+        gap_x: f32,
+        gap_y: f32,
+        offset_x: f32,
+    ) {
+        let rows = self.grid.rows;
+        let cols = self.grid.cols;
+        let header = 40.0;
+
+        // The selection may have been changed over the socket or by a reload
+        self.shelves.select(self.selected);
+
+        let area = ui.max_rect();
+        let shelf_height = header + tile_size.y + gap_y;
+        let mut y = area.center().y - shelf_height * rows.min(self.shelves.rows.len()) as f32 / 2.0;
+
+        let top = self.shelves.top(rows);
+        for row in top..(top + rows).min(self.shelves.rows.len()) {
+            let first = self.shelves.scroll(row, cols);
+            let shelf = &self.shelves.rows[row];
+            let title = shelf.title.clone();
+            let apps: Vec<usize> = shelf.apps.iter().skip(first).take(cols).copied().collect();
+            let more_left = first > 0;
+            let more_right = first + cols < shelf.apps.len();
+
+            let x = area.min.x + offset_x;
+            let color = if row == self.shelves.row {
+                egui::Color32::WHITE
+            } else {
+                egui::Color32::from_white_alpha(160)
+            };
+            ui.painter().text(
+                egui::pos2(x, y),
+                egui::Align2::LEFT_TOP,
+                title,
+                egui::FontId::proportional(32.0),
+                color,
+            );
+
+            let tiles_y = y + header;
+            let focus = (row == self.shelves.row).then(|| self.shelves.col(row) - first);
+            for (col, idx) in apps.into_iter().enumerate() {
+                let min = egui::pos2(x + col as f32 * (tile_size.x + gap_x), tiles_y);
+                let rect = egui::Rect::from_min_size(min, tile_size);
+                self.draw_tile(ui, rect, idx, focus == Some(col));
+            }
+
+            // Arrows where the shelf carries on off screen
+            let arrow_y = tiles_y + tile_size.y / 2.0;
+            let arrow = |pos: egui::Pos2, text: &str| {
+                ui.painter().text(
+                    pos,
+                    egui::Align2::CENTER_CENTER,
+                    text,
+                    egui::FontId::proportional(40.0),
+                    egui::Color32::from_white_alpha(180),
+                );
+            };
+            if more_left {
+                arrow(egui::pos2(x - gap_x, arrow_y), "<");
+            }
+            if more_right {
+                let end = x + cols as f32 * (tile_size.x + gap_x) - gap_x;
+                arrow(egui::pos2(end + gap_x, arrow_y), ">");
+            }
+
+            y += shelf_height;
+        }
+    }
+
+    // Arrows move, page keys and shoulder buttons flip pages, or scroll a shelf by a screenful
+    fn navigate(&mut self, actions: &Actions) {
+        let len = self.apps.len();
+        if len == 0 {
+            return;
+        }
+
+        if self.settings.layout == Layout::Shelves {
+            let shelves = &mut self.shelves;
+            let page = self.grid.cols;
+            shelves.select(self.selected);
+
+            if actions.contains(&Action::Right) {
+                shelves.right(1);
+            }
+            if actions.contains(&Action::Left) {
+                shelves.left(1);
+            }
+            if actions.contains(&Action::Down) {
+                shelves.down();
+            }
+            if actions.contains(&Action::Up) {
+                shelves.up();
+            }
+            if actions.contains(&Action::PageRight) {
+                shelves.right(page);
+            }
+            if actions.contains(&Action::PageLeft) {
+                shelves.left(page);
+            }
+
+            self.selected = shelves.selected().unwrap_or(self.selected);
+            return;
+        }
+
+        // Left and right along the Continue row, down goes back to where the grid was left
+        let strip = self.continue_strip().len();
+        if let Some(col) = self.continue_col {
+            if actions.contains(&Action::Down) || strip == 0 {
+                self.continue_col = None;
+                self.selected = self.grid_selected;
+                return;
+            }
+            let mut col = col.min(strip - 1);
+            if actions.contains(&Action::Right) {
+                col = (col + 1).min(strip - 1);
+            }
+            if actions.contains(&Action::Left) {
+                col = col.saturating_sub(1);
+            }
+            self.continue_col = Some(col);
+            self.selected = self.recent[col];
+            return;
+        }
+
+        // Up from the top row of a page
+        if actions.contains(&Action::Up)
+            && strip > 0
+            && self.selected % self.grid.page_size() < self.grid.cols
+        {
+            let col = (self.selected % self.grid.cols).min(strip - 1);
+            self.grid_selected = self.selected;
+            self.continue_col = Some(col);
+            self.selected = self.recent[col];
+            return;
+        }
+
+        if actions.contains(&Action::Right) {
+            self.selected = self.grid.right(self.selected, len);
+        }
+        if actions.contains(&Action::Left) {
+            self.selected = self.grid.left(self.selected);
+        }
+        if actions.contains(&Action::Down) {
+            self.selected = self.grid.down(self.selected, len);
+        }
+        if actions.contains(&Action::Up) {
+            self.selected = self.grid.up(self.selected);
+        }
+        if actions.contains(&Action::PageRight) {
+            self.selected = self.grid.next_page(self.selected, len);
+        }
+        if actions.contains(&Action::PageLeft) {
+            self.selected = self.grid.prev_page(self.selected);
+        }
+    }
+
+    // Runs on its own thread so a slow hook doesn't hold up the launcher
+    fn run_post_exit(&self, name: &str) {
+        let Some(entry) = self.apps.iter().find(|entry| entry.name == name) else {
+            return;
+        };
+        let hooks = &self.settings.hooks;
+        let Some(hook) = launch::hook(&entry.post_exit, &hooks.post_exit) else {
+            return;
+        };
+
+        let name = name.to_string();
+        let hook = hook.to_string();
+        let timeout = Duration::from_secs(hooks.timeout_secs);
+        std::thread::spawn(move || {
+            if let Err(e) = launch::run_hook(&name, "post_exit", &hook, timeout) {
+                eprintln!("{} post_exit hook failed: {}", name, e);
+            }
+        });
+    }
+
+    // Icons that passed the check at load time but turned out not to decode
+    fn report_broken_image(&mut self, path: &str, problem: String) {
+        let mut entries: Vec<String> = self
+            .apps
+            .iter()
+            .filter(|entry| shellexpand::tilde(&entry.icon) == path)
+            .map(|entry| entry.name.clone())
+            .collect();
+        if entries.is_empty() {
+            entries.push(path.to_string());
+        }
+
+        let problem = format!("{}: {}", path, problem);
+        for entry in entries {
+            let diagnostic = Diagnostic {
+                entry,
+                problem: problem.clone(),
+                skipped: false,
+            };
+            eprintln!("{}", diagnostic);
+            self.toasts.push(
+                format!("Couldn't show the image for {}", diagnostic.entry),
+                vec![problem.clone()],
+            );
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn gamepad_actions(&mut self) -> Actions {
+        let mut pressed = Actions::new();
+
+        self.gilrs.inc();
+
+        // Event queue catches taps shorter than a frame, raw events go to the
+        // rebinding screen while it is open
+        while let Some(ev) = self.gilrs.next_event() {
+            let gamepad = self.gilrs.gamepad(ev.id);
+
+            match ev.event {
+                EventType::Connected => {
+                    self.toasts.notify(format!(
+                        "{} connected, {}",
+                        gamepad.name(),
+                        power_string(gamepad.power_info())
+                    ));
+                    continue;
+                }
+                EventType::Disconnected => {
+                    self.toasts
+                        .notify(format!("{} disconnected", gamepad.name()));
+                    continue;
+                }
+                _ => {}
+            }
+
+            if let Some(rebinder) = &mut self.rebinder {
+                rebinder.feed(ev.id, gamepad.name(), &ev.event);
+            } else if let EventType::ButtonPressed(button, _) = ev.event {
+                pressed.extend(input::pressed(button, &self.input.bindings_for(&gamepad)));
+            }
+        }
+
+        // Polling state
+        let deadzone = self.settings.input.deadzone;
+        let mut held = Actions::new();
+        for (_id, gamepad) in self.gilrs.gamepads() {
+            held.extend(input::held(
+                &gamepad,
+                &self.input.bindings_for(&gamepad),
+                deadzone,
+            ));
+        }
+
+        // Edges and auto-repeat per action
+        let mut actions = self.repeater.update(
+            &held,
+            Duration::from_millis(self.settings.input.repeat_delay_ms),
+            Duration::from_millis(self.settings.input.repeat_interval_ms),
+        );
+        actions.extend(pressed);
+
+        if let Some(rebinder) = &mut self.rebinder {
+            rebinder.hold_menu(held.contains(&Action::Menu));
+            Actions::new()
+        } else {
+            actions
+        }
+    }
+
+    // True on the frame the home chord becomes held on any pad
+    fn home_pressed(&mut self) -> bool {
+        let home = &self.settings.home;
+        let deadzone = self.settings.input.deadzone;
+        let held = self
+            .gilrs
+            .gamepads()
+            .any(|(_id, gamepad)| input::chord_held(&gamepad, &home.chord, deadzone));
+
+        let pressed = held && !self.home_held;
+        self.home_held = held;
+        pressed
+    }
+
+    // True once the force quit chord has been held long enough, once per hold
+    fn force_quit_pressed(&mut self) -> bool {
+        let force_quit = &self.settings.force_quit;
+        let deadzone = self.settings.input.deadzone;
+        let held = self
+            .gilrs
+            .gamepads()
+            .any(|(_id, gamepad)| input::chord_held(&gamepad, &force_quit.chord, deadzone));
+
+        if !held {
+            self.force_quit_since = None;
+            return false;
+        }
+
+        let (since, fired) = self.force_quit_since.get_or_insert((Instant::now(), false));
+        if !*fired && since.elapsed() >= Duration::from_millis(force_quit.hold_ms) {
+            *fired = true;
+            return true;
+        }
+        false
+    }
+
+    // Brings the launcher back over whatever app is in front
+    fn go_home(&mut self, frame: &mut eframe::Frame) {
+        raise(frame);
+
+        let Some(name) = self.supervisor.foreground().map(|sup| sup.name.clone()) else {
+            return;
+        };
+        let result = match self.settings.home.on_home {
+            HomeAction::None => Ok(()),
+            HomeAction::Pause => self.supervisor.pause(&name),
+            HomeAction::Close => self.supervisor.terminate(&name),
+        };
+        if let Err(e) = result {
+            self.toasts
+                .push(format!("Failed to stop {}", name), vec![e.to_string()]);
+        }
+    }
+
+    // Stores what the rebinding screen captured as overrides for that controller
+    fn save_bindings(&mut self, rebinder: Rebinder) {
+        let Some((_, name)) = rebinder.controller else {
+            println!("No buttons were pressed, bindings unchanged");
+            return;
+        };
+
+        self.input
+            .controllers
+            .entry(name.clone())
+            .or_default()
+            .extend(rebinder.bindings);
+
+        match self.input.save(&config::config_path("bindings.json")) {
+            Ok(()) => println!("Saved bindings for {}", name),
+            Err(e) => self
+                .toasts
+                .push("Failed to save bindings.json", vec![e.to_string()]),
         }
     }
 }
@@ -128,52 +1045,195 @@ impl eframe::App for HtpcApp {
         // Update every 30s for clock
         ctx.request_repaint_after(std::time::Duration::from_secs(30));
 
+        // Reap apps that have exited
+        for exited in self.supervisor.poll() {
+            let runtime = exited.started.elapsed();
+            println!(
+                "{} (pid {}) exited with {} after {:.0?}",
+                exited.name, exited.pid, exited.status, runtime
+            );
+
+            if !exited.status.success() && runtime < EARLY_EXIT {
+                self.toasts.push(
+                    format!("{} exited with {}", exited.name, exited.status),
+                    exited.stderr_tail,
+                );
+            }
+
+            if let Some(dbus) = &self.dbus {
+                dbus.app_exited(&exited.name, exited.status.code());
+            }
+            self.run_post_exit(&exited.name);
+        }
+
+        for (name, raised) in self.supervisor.raised() {
+            if !raised {
+                self.toasts.push(
+                    format!("{} is running but its window couldn't be brought up", name),
+                    vec!["Raising windows needs xdotool on X11".to_string()],
+                );
+            }
+        }
+
+        // A command given when starting the launcher runs on the first frame
+        if let Some(command) = self.args.command.take()
+            && let Err(e) = self.handle(&command, frame)
+        {
+            self.toasts
+                .push(format!("Failed to {}", command.to_line()), vec![e]);
+        }
+
+        self.poll_launching();
+
+        // Commands sent by other processes
+        for request in self.inbox.requests() {
+            let result = self.handle(&request.command, frame);
+            // A launch still running its pre_launch hook answers once the app has started
+            if let (Ok(_), ipc::Command::Launch(name)) = (&result, &request.command)
+                && let Some(pending) = self
+                    .launching
+                    .iter_mut()
+                    .find(|pending| pending.name.eq_ignore_ascii_case(name))
+            {
+                pending.replies.push(request);
+                continue;
+            }
+            request.reply(result);
+        }
+
+        // Pick up edits to the config directory
+        if let Some(watcher) = &self.watcher {
+            for path in watcher.changed() {
+                match path.file_name().and_then(|name| name.to_str()) {
+                    Some("apps.json" | "settings.json" | "bindings.json") => {
+                        self.reload_at = Some(Instant::now() + RELOAD_DELAY)
+                    }
+                    Some("background.jpg") => self.bg_texture = None,
+                    _ => {}
+                }
+            }
+        }
+        if self.reload_at.is_some_and(|at| at <= Instant::now()) {
+            self.reload_at = None;
+            self.reload();
+        }
+
         let focused = frame.info().window_info.focused;
 
+        // Gamepads are read even while an app is in front so the home chord still works
+        let gamepad_actions = self.gamepad_actions();
+        let remote_actions = std::mem::take(&mut self.remote_actions);
+        if self.home_pressed() && !focused {
+            self.go_home(frame);
+        }
+
+        // Long press opens the close app overlay, over a hung fullscreen app too
+        if self.force_quit_pressed() {
+            let running: Vec<&str> = self
+                .supervisor
+                .running()
+                .map(|sup| sup.name.as_str())
+                .collect();
+            if running.is_empty() {
+                self.toasts.notify("No apps to close");
+            } else {
+                let foreground = self.supervisor.foreground().map(|sup| sup.name.as_str());
+                let row = running.iter().position(|name| Some(*name) == foreground);
+                self.force_quit = Some(row.unwrap_or(0));
+                if !focused {
+                    raise(frame);
+                }
+            }
+        }
+
         // Checks if the home screen is focused before taking input
         if focused {
             // Sets up gamepad/keyboard actions
-            let (gp_left, gp_right, gp_up, gp_down, gp_activate) = self.gamepad_actions();
-            let key_right = ctx.input(|i| i.key_pressed(egui::Key::ArrowRight));
-            let key_left = ctx.input(|i| i.key_pressed(egui::Key::ArrowLeft));
-            let key_down = ctx.input(|i| i.key_pressed(egui::Key::ArrowDown));
-            let key_up = ctx.input(|i| i.key_pressed(egui::Key::ArrowUp));
-            let key_enter = ctx.input(|i| i.key_pressed(egui::Key::Enter));
-
-            // 'c' closes app
-            if ctx.input(|i| i.key_pressed(egui::Key::C)) {
-                frame.close();
-                return;
-            }
+            let mut actions = gamepad_actions;
+            actions.extend(keyboard_actions(ctx));
+            actions.extend(remote_actions);
 
-            // Arrow keys move
-            if key_right || gp_right {
-                if self.selected + 1 < self.apps.len() && (self.selected + 1) % GRID_COLS != 0 {
-                    self.selected += 1;
+            // The rebinding screen takes all input until it finishes, Escape or holding
+            // Menu cancels it
+            if let Some(rebinder) = &mut self.rebinder {
+                rebinder.tick();
+                if ctx.input(|i| i.key_pressed(egui::Key::Escape)) || rebinder.is_cancelled() {
+                    self.rebinder = None;
+                } else if rebinder.is_done() {
+                    let rebinder = self.rebinder.take().unwrap();
+                    self.save_bindings(rebinder);
                 }
+                actions.clear();
             }
-            if key_left || gp_left {
-                if self.selected % GRID_COLS != 0 {
-                    self.selected -= 1;
+
+            // The close app overlay takes all input while open
+            if let Some(row) = self.force_quit {
+                let running: Vec<String> = self
+                    .supervisor
+                    .running()
+                    .map(|sup| sup.name.clone())
+                    .collect();
+                let row = row.min(running.len().saturating_sub(1));
+
+                if running.is_empty() || actions.contains(&Action::Back) {
+                    self.force_quit = None;
+                } else if actions.contains(&Action::Activate) {
+                    let name = &running[row];
+                    match self.supervisor.terminate(name) {
+                        Ok(()) => self.toasts.notify(format!("Closing {}", name)),
+                        Err(e) => self
+                            .toasts
+                            .push(format!("Failed to close {}", name), vec![e.to_string()]),
+                    }
+                    self.force_quit = None;
+                } else if actions.contains(&Action::Up) {
+                    self.force_quit = Some(row.saturating_sub(1));
+                } else if actions.contains(&Action::Down) {
+                    self.force_quit = Some((row + 1).min(running.len() - 1));
+                } else {
+                    self.force_quit = Some(row);
                 }
+                actions.clear();
             }
-            if key_down || gp_down {
-                let next = self.selected + GRID_COLS;
-                if next < self.apps.len() {
-                    self.selected = next;
-                }
+
+            if actions.contains(&Action::Quit) {
+                frame.close();
+                return;
             }
-            if key_up || gp_up {
-                if self.selected >= GRID_COLS {
-                    self.selected -= GRID_COLS;
-                }
+
+            self.navigate(&actions);
+
+            // 'd' shows config problems
+            if ctx.input(|i| i.key_pressed(egui::Key::D)) {
+                self.show_diagnostics = !self.show_diagnostics;
+            }
+
+            // F3 shows the debug overlay
+            if ctx.input(|i| i.key_pressed(egui::Key::F3)) {
+                self.show_debug = !self.show_debug;
             }
 
-            // Launch App
-            if key_enter || gp_activate {
-                self.animation_start = Some(std::time::Instant::now());
-                self.animation_idx = Some(self.selected);
-                self.launch(self.selected).expect("Failed to launch app");
+            // Menu opens the controller bindings screen
+            if actions.contains(&Action::Menu) {
+                self.rebinder = Some(Rebinder::new());
+            }
+
+            // Back and activate both close what is covering the grid
+            let activate = actions.contains(&Action::Activate);
+            if activate || actions.contains(&Action::Back) {
+                if self.show_diagnostics {
+                    self.show_diagnostics = false;
+                } else if !self.toasts.is_empty() {
+                    self.toasts.dismiss();
+                } else if activate {
+                    self.animation_start = Some(std::time::Instant::now());
+                    self.animation_idx = Some(self.selected);
+                    if let Err(e) = self.launch(self.selected) {
+                        let name = self.apps[self.selected].name.clone();
+                        self.toasts
+                            .push(format!("Failed to launch {}", name), vec![e.to_string()]);
+                    }
+                }
             }
         }
 
@@ -181,8 +1241,20 @@ impl eframe::App for HtpcApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             let available = ui.available_size();
 
-            let tile_width = available.x / GRID_COLS as f32 * 0.75;
-            let tile_height = available.y / GRID_ROWS as f32 * 0.75;
+            let grid = self.grid;
+            let in_grid = match self.continue_col {
+                Some(_) => self.grid_selected,
+                None => self.selected,
+            };
+            let page = grid.page_of(in_grid);
+            let pages = grid.pages(self.apps.len());
+
+            // The Continue row takes the space of one more row of tiles
+            let strip = self.continue_strip().to_vec();
+            let rows = grid.rows + usize::from(!strip.is_empty());
+
+            let tile_width = available.x / grid.cols as f32 * 0.75;
+            let tile_height = available.y / rows as f32 * 0.75;
 
             let tile_size = egui::vec2(tile_width, tile_height);
 
@@ -190,22 +1262,21 @@ impl eframe::App for HtpcApp {
             let tile_gap_x = 40.0;
             let tile_gap_y = 40.0;
 
-            let total_width = tile_width * GRID_COLS as f32;
-            let total_height = tile_height * GRID_ROWS as f32;
+            let total_width = tile_width * grid.cols as f32;
+            let total_height = tile_height * rows as f32;
             let offset_x = (available.x - total_width) / 2.0;
             let offset_y = (available.y - total_height) / 2.0;
 
-            // Load background
-            if self.bg_texture.is_none() {
-                if let Some(tex) = load_texture(
-                    ui,
-                    "background",
-                    shellexpand::tilde("~/.config/htpc_app_manager/background.jpg")
-                        .to_string()
-                        .as_str(),
-                ) {
-                    self.bg_texture = Some(tex);
-                }
+            // Load background at screen resolution, asked for every frame so it is
+            // decoded again if the window grows
+            for (path, e) in self.textures.collect(ctx) {
+                self.report_broken_image(&path, e);
+            }
+            if let Slot::Ready(tex) = self.textures.get(
+                &config::config_path("background.jpg"),
+                ctx.screen_rect().size() * ctx.pixels_per_point(),
+            ) {
+                self.bg_texture = Some(tex);
             }
 
             let screen_rect = ctx.screen_rect();
@@ -255,86 +1326,162 @@ impl eframe::App for HtpcApp {
                 egui::Color32::from_rgba_unmultiplied(0, 0, 0, 140),
             );
 
+            if self.settings.layout == Layout::Shelves {
+                self.draw_shelves(ui, tile_size, tile_gap_x, tile_gap_y, offset_x);
+                return;
+            }
+
             // Add top buffer
             ui.add_space(offset_y);
 
-            for row in 0..GRID_ROWS {
+            // Continue row pinned above the pages, titled in the space above it
+            if !strip.is_empty() {
                 ui.horizontal(|ui| {
                     ui.add_space(offset_x);
 
-                    for col in 0..GRID_COLS {
-                        let idx = row * GRID_COLS + col;
+                    for (col, idx) in strip.into_iter().enumerate() {
                         let (rect, _) = ui.allocate_exact_size(tile_size, egui::Sense::hover());
+                        if col == 0 {
+                            ui.painter().text(
+                                rect.left_top() - egui::vec2(0.0, 8.0),
+                                egui::Align2::LEFT_BOTTOM,
+                                "Continue",
+                                egui::FontId::proportional(32.0),
+                                egui::Color32::WHITE,
+                            );
+                        }
+                        self.draw_tile(ui, rect, idx, self.continue_col == Some(col));
+                        ui.add_space(tile_gap_x);
+                    }
+                });
+                ui.add_space(tile_gap_y);
+            }
 
-                        // Draw background
-                        if let Some(app) = self.apps.get(idx) {
-                            let bg_color = if idx == self.selected {
-                                ui.visuals().selection.bg_fill
-                            } else {
-                                ui.visuals().faint_bg_color
-                            };
-                            ui.painter().rect_filled(rect, 12.0, bg_color);
-
-                            // Flash animation on press
-                            if Some(idx) == self.animation_idx {
-                                if let Some(start) = self.animation_start {
-                                    let elapsed = start.elapsed().as_secs_f32();
-                                    let duration = 0.25;
-
-                                    if elapsed < duration {
-                                        // Flash overlay
-                                        let alpha = (1.0 - (elapsed / duration)).clamp(0.0, 1.0);
-                                        let flash = egui::Color32::from_rgba_unmultiplied(
-                                            255,
-                                            255,
-                                            255,
-                                            (200.0 * alpha) as u8,
-                                        );
-                                        ui.painter().rect_filled(rect, 12.0, flash);
-
-                                        ctx.request_repaint(); // Animate
-                                    } else {
-                                        self.animation_idx = None;
-                                        self.animation_start = None;
-                                    }
-                                }
-                            }
-
-                            // Draw icon
-                            if let Some(texture) =
-                                load_texture(ui, &format!("icon_{}", idx), &app.icon)
-                            {
-                                let padding = rect.width() * 0.10;
-
-                                let icon_rect = egui::Rect::from_min_max(
-                                    rect.min + egui::vec2(padding, padding),
-                                    rect.max - egui::vec2(padding, padding),
-                                );
-
-                                ui.painter().image(
-                                    texture.id(),
-                                    icon_rect,
-                                    egui::Rect::from_min_max(
-                                        egui::pos2(0.0, 0.0),
-                                        egui::pos2(1.0, 1.0),
-                                    ),
-                                    egui::Color32::WHITE,
-                                );
-                            }
+            for row in 0..grid.rows {
+                ui.horizontal(|ui| {
+                    ui.add_space(offset_x);
+
+                    for col in 0..grid.cols {
+                        let idx = page * grid.page_size() + row * grid.cols + col;
+                        let (rect, _) = ui.allocate_exact_size(tile_size, egui::Sense::hover());
+
+                        if idx < self.apps.len() {
+                            let focused = idx == self.selected && self.continue_col.is_none();
+                            self.draw_tile(ui, rect, idx, focused);
                         }
                         // Horizontal spacing between tiles
-                        if col < GRID_COLS - 1 {
+                        if col < grid.cols - 1 {
                             ui.add_space(tile_gap_x);
                         }
                     }
                 });
                 // Vertical spacing between tiles
-                if row < GRID_ROWS - 1 {
+                if row < grid.rows - 1 {
                     ui.add_space(tile_gap_y);
                 }
             }
+
+            // Page indicators
+            if pages > 1 {
+                let radius = 8.0;
+                let spacing = 32.0;
+                let center = egui::pos2(screen_rect.center().x, screen_rect.max.y - 40.0);
+                let start_x = center.x - spacing * (pages - 1) as f32 / 2.0;
+
+                for p in 0..pages {
+                    let pos = egui::pos2(start_x + spacing * p as f32, center.y);
+                    if p == page {
+                        ui.painter()
+                            .circle_filled(pos, radius, egui::Color32::WHITE);
+                    } else {
+                        ui.painter().circle_stroke(
+                            pos,
+                            radius,
+                            egui::Stroke::new(2.0, egui::Color32::from_white_alpha(120)),
+                        );
+                    }
+                }
+            }
         });
 
+        if self.show_diagnostics {
+            draw_diagnostics(ctx, &self.diagnostics);
+        }
+
+        if let Some(rebinder) = &self.rebinder {
+            draw_rebinding(ctx, rebinder);
+        }
+
+        if let Some(row) = self.force_quit {
+            let running: Vec<&str> = self
+                .supervisor
+                .running()
+                .map(|sup| sup.name.as_str())
+                .collect();
+            draw_force_quit(ctx, &running, row);
+        }
+
+        self.toasts.draw(ctx);
+
+        // Active controllers, or keyboard hints when there are none
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            "controller_layer".into(),
+        ));
+        let screen_rect = ctx.screen_rect();
+        let mut pos = egui::pos2(screen_rect.min.x + 20.0, screen_rect.max.y - 20.0);
+        let mut any_gamepad = false;
+
+        for (_id, gamepad) in self.gilrs.gamepads() {
+            any_gamepad = true;
+            let rect = painter.text(
+                pos,
+                egui::Align2::LEFT_BOTTOM,
+                format!(
+                    "{}  ({})",
+                    gamepad.name(),
+                    power_string(gamepad.power_info())
+                ),
+                egui::FontId::proportional(20.0),
+                egui::Color32::from_white_alpha(200),
+            );
+            pos.y -= rect.height() + 6.0;
+        }
+
+        if !any_gamepad {
+            painter.text(
+                pos,
+                egui::Align2::LEFT_BOTTOM,
+                "Arrows move · Enter launch · Esc back · PgUp/PgDn page · M bindings · C quit",
+                egui::FontId::proportional(20.0),
+                egui::Color32::from_white_alpha(200),
+            );
+        }
+
+        if self.show_debug {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                "debug_layer".into(),
+            ));
+            let mut text = format!(
+                "textures: {} cached, {} decoding, {} hits, {} misses",
+                self.textures.len(),
+                self.textures.pending(),
+                self.textures.hits,
+                self.textures.misses
+            );
+            if let Some(url) = &self.remote_url {
+                text += &format!("\nphone remote: {}", url);
+            }
+            painter.text(
+                ctx.screen_rect().left_top() + egui::vec2(20.0, 20.0),
+                egui::Align2::LEFT_TOP,
+                text,
+                egui::FontId::monospace(18.0),
+                egui::Color32::YELLOW,
+            );
+        }
+
         // Clock
         let now = chrono::Local::now();
         let time_string = now.format("%I:%M %p").to_string();
@@ -356,17 +1503,338 @@ impl eframe::App for HtpcApp {
     }
 }
 
-// Load icon texture from file
-fn load_texture(ui: &egui::Ui, name: &str, path: &str) -> Option<egui::TextureHandle> {
-    let path = shellexpand::tilde(path).to_string();
-    let data = fs::read(path).ok()?;
-    let image = image::load_from_memory(&data).ok()?.to_rgba8();
-    let size = [image.width() as usize, image.height() as usize];
-    let color_image = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
-    Some(ui.ctx().load_texture(name, color_image, Default::default()))
+fn raise(frame: &mut eframe::Frame) {
+    frame.set_visible(true);
+    frame.set_minimized(false);
+    frame.focus();
+}
+
+fn power_string(power: gilrs::PowerInfo) -> String {
+    match power {
+        gilrs::PowerInfo::Wired => "wired".to_string(),
+        gilrs::PowerInfo::Discharging(level) => format!("battery {}%", level),
+        gilrs::PowerInfo::Charging(level) => format!("charging {}%", level),
+        gilrs::PowerInfo::Charged => "charged".to_string(),
+        gilrs::PowerInfo::Unknown => "battery unknown".to_string(),
+    }
+}
+
+fn keyboard_actions(ctx: &egui::Context) -> Actions {
+    let keys = [
+        (egui::Key::ArrowUp, Action::Up),
+        (egui::Key::ArrowDown, Action::Down),
+        (egui::Key::ArrowLeft, Action::Left),
+        (egui::Key::ArrowRight, Action::Right),
+        (egui::Key::Enter, Action::Activate),
+        (egui::Key::Escape, Action::Back),
+        (egui::Key::M, Action::Menu),
+        (egui::Key::PageUp, Action::PageLeft),
+        (egui::Key::PageDown, Action::PageRight),
+        (egui::Key::C, Action::Quit), // 'c' closes app
+    ];
+
+    ctx.input(|i| {
+        keys.iter()
+            .filter(|(key, _)| i.key_pressed(*key))
+            .map(|(_, action)| *action)
+            .collect()
+    })
+}
+
+// CLI options take priority over settings.json
+fn grid_for(settings: &Settings, args: &cli::Args) -> Grid {
+    Grid::new(
+        args.rows.unwrap_or(settings.grid.rows),
+        args.cols.unwrap_or(settings.grid.cols),
+    )
+}
+
+// Single line caption, cut off with an ellipsis and backed so it reads over any icon
+fn draw_label(painter: &egui::Painter, rect: egui::Rect, text: &str, font_size: f32) {
+    let mut job = egui::text::LayoutJob::simple_singleline(
+        text.to_string(),
+        egui::FontId::proportional(font_size),
+        egui::Color32::WHITE,
+    );
+    job.wrap = egui::epaint::text::TextWrapping {
+        max_width: rect.width() - font_size,
+        max_rows: 1,
+        break_anywhere: true,
+        overflow_character: Some('…'),
+    };
+    let galley = painter.fonts(|f| f.layout_job(job));
+
+    let text_rect = egui::Rect::from_center_size(rect.center(), galley.size());
+    painter.rect_filled(
+        text_rect.expand2(egui::vec2(font_size * 0.4, font_size * 0.15)),
+        font_size * 0.3,
+        egui::Color32::from_black_alpha(170),
+    );
+    painter.galley_with_color(
+        text_rect.min + egui::vec2(1.5, 1.5),
+        galley.clone(),
+        egui::Color32::BLACK,
+    );
+    painter.galley(text_rect.min, galley);
+}
+
+// Confirmation for closing one of the apps the launcher started
+fn draw_force_quit(ctx: &egui::Context, running: &[&str], row: usize) {
+    let Some(selected) = running.get(row) else {
+        return;
+    };
+    let screen_rect = ctx.screen_rect();
+
+    egui::Area::new("force_quit")
+        .order(egui::Order::Foreground)
+        .fixed_pos(screen_rect.min)
+        .show(ctx, |ui| {
+            ui.painter().rect_filled(
+                screen_rect,
+                0.0,
+                egui::Color32::from_rgba_unmultiplied(0, 0, 0, 220),
+            );
+            ui.set_min_size(screen_rect.size());
+
+            ui.vertical_centered(|ui| {
+                ui.add_space(120.0);
+                ui.label(
+                    egui::RichText::new(format!("Close {}?", selected))
+                        .size(56.0)
+                        .color(egui::Color32::WHITE),
+                );
+                ui.add_space(40.0);
+
+                for (i, name) in running.iter().enumerate() {
+                    let text = egui::RichText::new(*name).size(32.0);
+                    let text = if i == row {
+                        text.color(egui::Color32::WHITE)
+                            .background_color(ui.visuals().selection.bg_fill)
+                    } else {
+                        text.color(egui::Color32::GRAY)
+                    };
+                    ui.label(text);
+                }
+
+                ui.add_space(40.0);
+                ui.label(
+                    egui::RichText::new("A / Enter to close · B / Esc to cancel")
+                        .size(20.0)
+                        .color(egui::Color32::GRAY),
+                );
+            });
+        });
+}
+
+// Full screen prompt for the action being rebound
+fn draw_rebinding(ctx: &egui::Context, rebinder: &Rebinder) {
+    let screen_rect = ctx.screen_rect();
+
+    egui::Area::new("rebinding")
+        .order(egui::Order::Foreground)
+        .fixed_pos(screen_rect.min)
+        .show(ctx, |ui| {
+            ui.painter().rect_filled(
+                screen_rect,
+                0.0,
+                egui::Color32::from_rgba_unmultiplied(0, 0, 0, 220),
+            );
+            ui.set_min_size(screen_rect.size());
+
+            ui.vertical_centered(|ui| {
+                ui.add_space(80.0);
+                ui.label(
+                    egui::RichText::new("Controller bindings")
+                        .size(48.0)
+                        .color(egui::Color32::WHITE),
+                );
+                if let Some((_, name)) = &rebinder.controller {
+                    ui.label(
+                        egui::RichText::new(name)
+                            .size(24.0)
+                            .color(egui::Color32::GRAY),
+                    );
+                }
+                ui.add_space(60.0);
+
+                if let Some(action) = rebinder.current() {
+                    ui.label(
+                        egui::RichText::new(format!("Press a button for {}", action.label()))
+                            .size(40.0)
+                            .color(egui::Color32::WHITE),
+                    );
+
+                    let left =
+                        input::REBIND_TIMEOUT.saturating_sub(rebinder.step_started.elapsed());
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Keeping the current binding in {}s",
+                            left.as_secs() + 1
+                        ))
+                        .size(20.0)
+                        .color(egui::Color32::GRAY),
+                    );
+                }
+
+                ui.add_space(40.0);
+                for (action, bindings) in &rebinder.bindings {
+                    let names: Vec<String> = bindings.iter().map(|b| String::from(*b)).collect();
+                    ui.label(
+                        egui::RichText::new(format!("{}: {}", action.label(), names.join(", ")))
+                            .size(22.0)
+                            .color(egui::Color32::LIGHT_GRAY),
+                    );
+                }
+
+                ui.add_space(30.0);
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Press Escape or hold {} for {}s to cancel",
+                        Action::Menu.label(),
+                        input::REBIND_CANCEL_HOLD.as_secs()
+                    ))
+                    .size(20.0)
+                    .color(egui::Color32::GRAY),
+                );
+            });
+        });
+}
+
+// Full screen list of problems found in apps.json
+fn draw_diagnostics(ctx: &egui::Context, diagnostics: &[Diagnostic]) {
+    let screen_rect = ctx.screen_rect();
+
+    egui::Area::new("diagnostics")
+        .order(egui::Order::Foreground)
+        .fixed_pos(screen_rect.min)
+        .show(ctx, |ui| {
+            ui.painter().rect_filled(
+                screen_rect,
+                0.0,
+                egui::Color32::from_rgba_unmultiplied(0, 0, 0, 220),
+            );
+            ui.set_min_size(screen_rect.size());
+
+            ui.vertical_centered(|ui| {
+                ui.add_space(80.0);
+                ui.label(
+                    egui::RichText::new("Config diagnostics")
+                        .size(48.0)
+                        .color(egui::Color32::WHITE),
+                );
+                ui.add_space(30.0);
+
+                if diagnostics.is_empty() {
+                    ui.label(
+                        egui::RichText::new("No problems found")
+                            .size(24.0)
+                            .color(egui::Color32::LIGHT_GRAY),
+                    );
+                }
+
+                for diagnostic in diagnostics {
+                    let color = if diagnostic.skipped {
+                        egui::Color32::from_rgb(240, 110, 110)
+                    } else {
+                        egui::Color32::from_rgb(240, 200, 100)
+                    };
+                    ui.label(
+                        egui::RichText::new(diagnostic.to_string())
+                            .size(24.0)
+                            .color(color),
+                    );
+                }
+
+                ui.add_space(30.0);
+                ui.label(
+                    egui::RichText::new("Press A / Enter to close, D to reopen")
+                        .size(20.0)
+                        .color(egui::Color32::GRAY),
+                );
+            });
+        });
+}
+
+// Merges the apps a source finds into apps.json, a running launcher picks them up on its own
+fn import(source: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let settings = config::load_settings(&config::config_path("settings.json"))?;
+    let found = match source {
+        "desktop" => config::desktop_apps(&settings),
+        "steam" => steam::scan(&settings.steam),
+        "flatpak" => {
+            let (pinned, rest) = flatpak::scan(&settings.flatpak);
+            pinned.into_iter().chain(rest).collect()
+        }
+        "snap" => {
+            let (pinned, rest) = snap::scan(&settings.snap);
+            pinned.into_iter().chain(rest).collect()
+        }
+        _ => return Err(format!("unknown source '{}'", source).into()),
+    };
+
+    config::merge_into_json(&config::config_path("apps.json"), found)
 }
 
 fn main() {
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(args) if args.help => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(source) = &args.import {
+        match import(source) {
+            Ok(added) if added.is_empty() => println!("No new apps found"),
+            Ok(added) => println!("Added to apps.json: {}", added.join(", ")),
+            Err(e) => {
+                eprintln!("Import failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // A second launcher passes its command to the first instead of opening a window
+    let lock = match ipc::lock() {
+        Ok(Some(_)) if args.command.as_ref().is_some_and(|c| !c.starts_launcher()) => {
+            eprintln!("htpc_app_manager is not running");
+            std::process::exit(1);
+        }
+        Ok(Some(lock)) => Some(lock),
+        Ok(None) => {
+            let command = args.command.unwrap_or(ipc::Command::Show);
+            // Long enough for the running launcher to get through a pre_launch hook
+            let hooks = config::load_settings(&config::config_path("settings.json"))
+                .map(|settings| settings.hooks)
+                .unwrap_or_default();
+            match ipc::send(
+                &command,
+                ipc::reply_timeout(Duration::from_secs(hooks.timeout_secs)),
+            ) {
+                Ok(reply) => {
+                    if !reply.is_empty() {
+                        println!("{}", reply);
+                    }
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Not checking for a running launcher: {}", e);
+            None
+        }
+    };
+
     let options = eframe::NativeOptions {
         fullscreen: true,
         always_on_top: false,
@@ -376,6 +1844,8 @@ fn main() {
     let _ = eframe::run_native(
         "HTPC App Manager",
         options,
-        Box::new(|_cc| Box::new(HtpcApp::new().expect("Failed to create apps"))),
+        Box::new(|cc| {
+            Box::new(HtpcApp::new(&cc.egui_ctx, args, lock).expect("Failed to create apps"))
+        }),
     );
 }
@@ -0,0 +1,286 @@
+use crate::{
+    config::MqttSettings,
+    ipc::{Command, Remote},
+};
+use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+// How often the launcher's state is checked for changes to publish
+const STATE_INTERVAL: Duration = Duration::from_secs(1);
+
+// Reconnect attempts back off up to this long between tries
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// Publishes the launcher's state under <prefix>/ and takes commands from <prefix>/command/<verb>,
+// the payload being the command's argument, e.g. <prefix>/command/launch with "Kodi"
+pub fn start(settings: &MqttSettings, remote: Remote) {
+    let prefix = settings.topic_prefix.trim_end_matches('/').to_string();
+
+    let mut options = MqttOptions::new(&settings.client_id, &settings.host, settings.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(
+        format!("{}/available", prefix),
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let Some(username) = &settings.username {
+        options.set_credentials(username, settings.password.clone().unwrap_or_default());
+    }
+
+    let (client, mut connection) = Client::new(options, 16);
+
+    // Last state sent, cleared on reconnect so the broker gets it again
+    let published = Arc::new(Mutex::new(None));
+
+    {
+        let client = client.clone();
+        let remote = remote.clone();
+        let prefix = prefix.clone();
+        let published = Arc::clone(&published);
+        thread::spawn(move || {
+            loop {
+                publish_state(&client, &remote, &prefix, &published);
+                thread::sleep(STATE_INTERVAL);
+            }
+        });
+    }
+
+    let host = format!("{}:{}", settings.host, settings.port);
+    thread::spawn(move || {
+        let mut backoff = Duration::from_secs(1);
+
+        for event in connection.iter() {
+            match event {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    println!("Connected to MQTT broker at {}", host);
+                    backoff = Duration::from_secs(1);
+                    *published.lock().unwrap() = None;
+
+                    let _ = client.try_publish(
+                        format!("{}/available", prefix),
+                        QoS::AtLeastOnce,
+                        true,
+                        "online",
+                    );
+                    if let Err(e) =
+                        client.try_subscribe(format!("{}/command/+", prefix), QoS::AtLeastOnce)
+                    {
+                        eprintln!("MQTT subscribe failed: {}", e);
+                    }
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    let Some(command) = command_for(&prefix, &publish.topic, &publish.payload)
+                    else {
+                        continue;
+                    };
+
+                    // A launch can wait on its pre_launch hook, the event loop has to keep
+                    // polling meanwhile or pings stop and the broker drops the connection
+                    let remote = remote.clone();
+                    thread::spawn(move || {
+                        let result = command.and_then(|command| remote.call(command));
+                        if let Err(e) = result {
+                            eprintln!("MQTT command {} failed: {}", publish.topic, e);
+                        }
+                    });
+                }
+                Ok(_) => {}
+                // The next poll reconnects, wait first so a down broker isn't hammered
+                Err(e) => {
+                    eprintln!(
+                        "MQTT connection to {} lost: {}, retrying in {:?}",
+                        host, e, backoff
+                    );
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+        }
+    });
+}
+
+// None for topics that aren't under <prefix>/command/
+fn command_for(prefix: &str, topic: &str, payload: &[u8]) -> Option<Result<Command, String>> {
+    let verb = topic.strip_prefix(&format!("{}/command/", prefix))?;
+    let arg = String::from_utf8_lossy(payload);
+    Some(Command::parse(&format!("{} {}", verb, arg)))
+}
+
+// What gets published, from the launcher's status reply
+fn state_for(status: &serde_json::Value) -> serde_json::Value {
+    let running = status["foreground"].as_str().unwrap_or_default();
+    serde_json::json!({
+        "selected": status["selected"]["name"].as_str().unwrap_or_default(),
+        "running": running,
+        "activity": if running.is_empty() { "idle" } else { "active" },
+    })
+}
+
+// Retained so Home Assistant picks the state up when it connects later
+fn publish_state(
+    client: &Client,
+    remote: &Remote,
+    prefix: &str,
+    published: &Mutex<Option<serde_json::Value>>,
+) {
+    let Ok(status) = remote.call(Command::Status) else {
+        return;
+    };
+    let Ok(status) = serde_json::from_str::<serde_json::Value>(&status) else {
+        return;
+    };
+
+    let state = state_for(&status);
+
+    let mut published = published.lock().unwrap();
+    if published.as_ref() == Some(&state) {
+        return;
+    }
+
+    let mut sent = client
+        .try_publish(
+            format!("{}/state", prefix),
+            QoS::AtLeastOnce,
+            true,
+            state.to_string(),
+        )
+        .is_ok();
+    for key in ["selected", "running", "activity"] {
+        sent &= client
+            .try_publish(
+                format!("{}/{}", prefix, key),
+                QoS::AtLeastOnce,
+                true,
+                state[key].as_str().unwrap_or_default(),
+            )
+            .is_ok();
+    }
+
+    // Try again next time if the request queue was full
+    if sent {
+        *published = Some(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input::Action, ipc::Inbox};
+    use eframe::egui;
+    use std::time::Instant;
+
+    #[test]
+    fn maps_command_topics_and_payloads() {
+        assert_eq!(
+            command_for("htpc", "htpc/command/launch", b"Kodi"),
+            Some(Ok(Command::Launch("Kodi".to_string())))
+        );
+        assert_eq!(
+            command_for("htpc", "htpc/command/press", b"page-left"),
+            Some(Ok(Command::Press(Action::PageLeft)))
+        );
+        assert_eq!(
+            command_for("htpc", "htpc/command/reload", b""),
+            Some(Ok(Command::Reload))
+        );
+        assert!(matches!(
+            command_for("htpc", "htpc/command/launch", b""),
+            Some(Err(_))
+        ));
+        assert_eq!(command_for("htpc", "htpc/state", b"{}"), None);
+        assert_eq!(command_for("htpc", "other/command/launch", b"Kodi"), None);
+    }
+
+    #[test]
+    fn state_follows_the_foreground_app() {
+        let status = serde_json::json!({
+            "selected": { "index": 1, "name": "Jellyfin" },
+            "foreground": "Kodi",
+            "children": [],
+        });
+        assert_eq!(
+            state_for(&status),
+            serde_json::json!({ "selected": "Jellyfin", "running": "Kodi", "activity": "active" })
+        );
+
+        let idle = serde_json::json!({ "selected": null, "foreground": null, "children": [] });
+        assert_eq!(
+            state_for(&idle),
+            serde_json::json!({ "selected": "", "running": "", "activity": "idle" })
+        );
+    }
+
+    // Needs a broker such as mosquitto on localhost:1883
+    #[test]
+    #[ignore]
+    fn talks_to_a_local_broker() {
+        let prefix = format!("htpc_test_{}", std::process::id());
+        let settings = MqttSettings {
+            enabled: true,
+            client_id: format!("{}_launcher", prefix),
+            topic_prefix: prefix.clone(),
+            ..MqttSettings::default()
+        };
+        let inbox = Inbox::new(&egui::Context::default(), Duration::from_secs(1));
+        start(&settings, inbox.remote());
+
+        let options = MqttOptions::new(format!("{}_test", prefix), "localhost", 1883);
+        let (client, mut connection) = Client::new(options, 16);
+        client
+            .subscribe(format!("{}/state", prefix), QoS::AtLeastOnce)
+            .unwrap();
+        let (state_tx, state_rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            for event in connection.iter() {
+                if let Ok(Event::Incoming(Packet::Publish(publish))) = event {
+                    let _ = state_tx.send(publish.payload.to_vec());
+                }
+            }
+        });
+
+        // Stand in for the UI thread
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut launched = None;
+        let mut state = None;
+        while (launched.is_none() || state.is_none()) && Instant::now() < deadline {
+            for request in inbox.requests() {
+                match &request.command {
+                    Command::Status => request.reply(Ok(serde_json::json!({
+                        "selected": { "index": 0, "name": "Kodi" },
+                        "foreground": null,
+                        "children": [],
+                    })
+                    .to_string())),
+                    Command::Launch(name) => {
+                        launched = Some(name.clone());
+                        request.reply(Ok(String::new()));
+                    }
+                    _ => request.reply(Ok(String::new())),
+                }
+            }
+            // The launcher subscribes before it publishes, so commands reach it from here on
+            if state.is_none()
+                && let Ok(payload) = state_rx.try_recv()
+            {
+                state = serde_json::from_slice::<serde_json::Value>(&payload).ok();
+                client
+                    .publish(
+                        format!("{}/command/launch", prefix),
+                        QoS::AtLeastOnce,
+                        false,
+                        "Kodi",
+                    )
+                    .unwrap();
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        assert_eq!(launched.as_deref(), Some("Kodi"));
+        assert_eq!(state.unwrap()["selected"], "Kodi");
+    }
+}
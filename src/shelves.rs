@@ -0,0 +1,294 @@
+use crate::config::AppEntry;
+
+// Apps without a category end up on this shelf
+const UNCATEGORIZED: &str = "Apps";
+
+pub struct Shelf {
+    pub title: String,
+    pub apps: Vec<usize>, // Indexes into the app list
+    recent: bool,         // The Continue shelf, never merged with a category of the same name
+}
+
+// Shelf keys and the app name each was focused on
+pub struct Focus {
+    row: Option<(String, bool)>,
+    apps: Vec<((String, bool), String)>,
+}
+
+// One row per category, each remembering the tile it was left on
+pub struct Shelves {
+    pub rows: Vec<Shelf>,
+    pub row: usize,
+    focus: Vec<usize>,
+    // First tile drawn in each row, and the first row drawn
+    scroll: Vec<usize>,
+    top: usize,
+}
+
+impl Shelves {
+    // Shelves come in the order their category first appears in the app list,
+    // after a Continue shelf of recently launched apps if there are any
+    pub fn new(apps: &[AppEntry], recent: Vec<usize>) -> Self {
+        let mut rows: Vec<Shelf> = Vec::new();
+        if !recent.is_empty() {
+            rows.push(Shelf {
+                title: "Continue".to_string(),
+                apps: recent,
+                recent: true,
+            });
+        }
+
+        for (idx, app) in apps.iter().enumerate() {
+            let title = app.category.as_deref().unwrap_or(UNCATEGORIZED);
+            match rows
+                .iter_mut()
+                .find(|shelf| shelf.title == title && !shelf.recent)
+            {
+                Some(shelf) => shelf.apps.push(idx),
+                None => rows.push(Shelf {
+                    title: title.to_string(),
+                    apps: vec![idx],
+                    recent: false,
+                }),
+            }
+        }
+
+        Self {
+            focus: vec![0; rows.len()],
+            scroll: vec![0; rows.len()],
+            rows,
+            row: 0,
+            top: 0,
+        }
+    }
+
+    // Which app each shelf was on, by name so it survives the apps being reordered
+    pub fn focus(&self, apps: &[AppEntry]) -> Focus {
+        let key = |shelf: &Shelf| (shelf.title.clone(), shelf.recent);
+        Focus {
+            row: self.rows.get(self.row).map(key),
+            apps: self
+                .rows
+                .iter()
+                .zip(&self.focus)
+                .filter_map(|(shelf, col)| {
+                    let app = apps.get(*shelf.apps.get(*col)?)?;
+                    Some((key(shelf), app.name.clone()))
+                })
+                .collect(),
+        }
+    }
+
+    pub fn refocus(&mut self, apps: &[AppEntry], focus: &Focus) {
+        let find = |rows: &[Shelf], key: &(String, bool)| {
+            rows.iter()
+                .position(|shelf| shelf.title == key.0 && shelf.recent == key.1)
+        };
+
+        for (key, name) in &focus.apps {
+            let Some(row) = find(&self.rows, key) else {
+                continue;
+            };
+            if let Some(col) = self.rows[row]
+                .apps
+                .iter()
+                .position(|idx| apps[*idx].name == *name)
+            {
+                self.focus[row] = col;
+            }
+        }
+        if let Some(row) = focus.row.as_ref().and_then(|key| find(&self.rows, key)) {
+            self.row = row;
+        }
+    }
+
+    // Position of the focused tile within a row
+    pub fn col(&self, row: usize) -> usize {
+        self.focus[row]
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        let shelf = self.rows.get(self.row)?;
+        shelf.apps.get(self.focus[self.row]).copied()
+    }
+
+    // Moves focus onto an app chosen some other way, keeping it if already there
+    pub fn select(&mut self, idx: usize) {
+        if self.selected() == Some(idx) {
+            return;
+        }
+        for (row, shelf) in self.rows.iter().enumerate() {
+            if let Some(col) = shelf.apps.iter().position(|app| *app == idx) {
+                self.row = row;
+                self.focus[row] = col;
+                return;
+            }
+        }
+    }
+
+    pub fn left(&mut self, by: usize) {
+        if let Some(col) = self.focus.get_mut(self.row) {
+            *col = col.saturating_sub(by);
+        }
+    }
+
+    pub fn right(&mut self, by: usize) {
+        if let (Some(col), Some(shelf)) = (self.focus.get_mut(self.row), self.rows.get(self.row)) {
+            *col = (*col + by).min(shelf.apps.len() - 1);
+        }
+    }
+
+    // Rows keep their own focus, so coming back lands on the same tile
+    pub fn up(&mut self) {
+        self.row = self.row.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        if self.row + 1 < self.rows.len() {
+            self.row += 1;
+        }
+    }
+
+    // First tile to draw in a row so its focused tile is among the visible ones
+    pub fn scroll(&mut self, row: usize, visible: usize) -> usize {
+        let focus = self.focus[row];
+        let scroll = &mut self.scroll[row];
+        if focus < *scroll {
+            *scroll = focus;
+        } else if focus >= *scroll + visible {
+            *scroll = focus + 1 - visible;
+        }
+        *scroll
+    }
+
+    // First row to draw so the focused row is among the visible ones
+    pub fn top(&mut self, visible: usize) -> usize {
+        if self.row < self.top {
+            self.top = self.row;
+        } else if self.row >= self.top + visible {
+            self.top = self.row + 1 - visible;
+        }
+        self.top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(name: &str, category: Option<&str>) -> AppEntry {
+        AppEntry {
+            name: name.to_string(),
+            run: None,
+            command: None,
+            icon: String::new(),
+            category: category.map(str::to_string),
+            pre_launch: None,
+            post_exit: None,
+        }
+    }
+
+    fn apps() -> Vec<AppEntry> {
+        vec![
+            app("Kodi", Some("Streaming")),
+            app("Portal", Some("Game")),
+            app("Jellyfin", Some("Streaming")),
+            app("Celeste", Some("Game")),
+            app("Plex", Some("Streaming")),
+            app("Terminal", None),
+        ]
+    }
+
+    #[test]
+    fn groups_apps_by_category_in_order_of_appearance() {
+        let shelves = Shelves::new(&apps(), Vec::new());
+        let rows: Vec<(&str, &[usize])> = shelves
+            .rows
+            .iter()
+            .map(|shelf| (shelf.title.as_str(), shelf.apps.as_slice()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("Streaming", &[0, 2, 4][..]),
+                ("Game", &[1, 3][..]),
+                ("Apps", &[5][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn each_row_keeps_its_own_column() {
+        let mut shelves = Shelves::new(&apps(), Vec::new());
+        shelves.right(2);
+        assert_eq!(shelves.selected(), Some(4));
+
+        shelves.down();
+        assert_eq!(shelves.selected(), Some(1));
+        shelves.right(5);
+        assert_eq!(shelves.selected(), Some(3));
+
+        shelves.up();
+        assert_eq!(shelves.selected(), Some(4));
+        shelves.down();
+        assert_eq!(shelves.selected(), Some(3));
+    }
+
+    #[test]
+    fn select_moves_to_the_app_and_keeps_a_focus_already_on_it() {
+        let mut shelves = Shelves::new(&apps(), vec![2]);
+        shelves.select(3);
+        assert_eq!((shelves.row, shelves.selected()), (2, Some(3)));
+
+        // Jellyfin is on Continue and Streaming, whichever has the focus keeps it
+        shelves.select(2);
+        assert_eq!((shelves.row, shelves.selected()), (0, Some(2)));
+        shelves.down();
+        shelves.right(1);
+        shelves.select(2);
+        assert_eq!((shelves.row, shelves.selected()), (1, Some(2)));
+    }
+
+    #[test]
+    fn focus_survives_a_reorder() {
+        let before = apps();
+        let mut shelves = Shelves::new(&before, Vec::new());
+        shelves.right(1);
+        shelves.down();
+        shelves.right(1);
+        let focus = shelves.focus(&before);
+
+        // Games first now, and in a different order
+        let mut after = before.clone();
+        after.reverse();
+        let mut shelves = Shelves::new(&after, Vec::new());
+        shelves.refocus(&after, &focus);
+
+        assert_eq!(shelves.rows[shelves.row].title, "Game");
+        assert_eq!(after[shelves.selected().unwrap()].name, "Celeste");
+        let streaming = shelves
+            .rows
+            .iter()
+            .position(|shelf| shelf.title == "Streaming")
+            .unwrap();
+        shelves.row = streaming;
+        assert_eq!(after[shelves.selected().unwrap()].name, "Jellyfin");
+    }
+
+    #[test]
+    fn continue_stays_apart_from_a_category_of_the_same_name() {
+        let mut apps = apps();
+        apps.push(app("Netflix", Some("Continue")));
+        apps.push(app("Hulu", Some("Continue")));
+
+        let shelves = Shelves::new(&apps, vec![3, 0]);
+        let rows: Vec<(&str, bool, &[usize])> = shelves
+            .rows
+            .iter()
+            .map(|shelf| (shelf.title.as_str(), shelf.recent, shelf.apps.as_slice()))
+            .collect();
+        assert_eq!(rows[0], ("Continue", true, &[3, 0][..]));
+        assert_eq!(rows[4], ("Continue", false, &[6, 7][..]));
+        assert_eq!(rows.len(), 5);
+    }
+}
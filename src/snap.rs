@@ -0,0 +1,46 @@
+use crate::{
+    config::{AppEntry, DiscoverySettings},
+    desktop,
+};
+use std::{fs, path::Path};
+
+// snapd exports each snap app's .desktop file here as <snap>_<app>.desktop
+pub const APPLICATIONS: &str = "/var/lib/snapd/desktop/applications";
+
+// The wrappers snapd puts on PATH
+const BIN: &str = "/snap/bin";
+
+// Installed snaps that have a desktop entry and a /snap/bin wrapper to launch through,
+// as the pinned ones and the rest
+pub fn scan(settings: &DiscoverySettings) -> (Vec<AppEntry>, Vec<AppEntry>) {
+    let mut found = Vec::new();
+
+    for path in desktop::desktop_files(Path::new(APPLICATIONS)) {
+        let Some(stem) = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+        else {
+            continue;
+        };
+        let snap = stem.split_once('_').map_or(stem.as_str(), |(snap, _)| snap);
+
+        let Some(entry) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|file| desktop::parse(&file))
+            .filter(|entry| !entry.no_display)
+        else {
+            continue;
+        };
+
+        // Exec is usually "env BAMF_DESKTOP_FILE_HINT=... /snap/bin/<app>", anything
+        // that doesn't go through a wrapper isn't a snap app
+        if !entry.exec.iter().any(|arg| arg.starts_with(BIN)) {
+            continue;
+        }
+        if let Some(app) = entry.to_app() {
+            found.push((snap.to_string(), app));
+        }
+    }
+
+    settings.arrange(found)
+}
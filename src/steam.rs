@@ -0,0 +1,439 @@
+use crate::config::{AppEntry, LaunchCommand, SteamSettings};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Where Steam keeps its files for a native and a Flatpak install
+const ROOTS: [&str; 3] = [
+    "~/.local/share/Steam",
+    "~/.steam/steam",
+    "~/.var/app/com.valvesoftware.Steam/.local/share/Steam",
+];
+
+// Installed alongside games but not something to launch from the couch
+const TOOLS: [&str; 3] = ["Proton", "Steam Linux Runtime", "Steamworks Common"];
+
+// Artwork in order of preference, the portrait capsule suits a tile best
+const ART: [&str; 3] = ["library_600x900.jpg", "header.jpg", "logo.png"];
+
+// StateFlags bit set once a game is fully installed
+const FULLY_INSTALLED: u32 = 4;
+
+// A node of Valve's KeyValues text format
+#[derive(Debug)]
+enum Vdf {
+    Str(String),
+    Map(Vec<(String, Vdf)>),
+}
+
+impl Vdf {
+    // Valve isn't consistent about key case between files
+    fn get(&self, key: &str) -> Option<&Vdf> {
+        match self {
+            Vdf::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            Vdf::Str(_) => None,
+        }
+    }
+
+    fn str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            Vdf::Str(s) => Some(s),
+            Vdf::Map(_) => None,
+        }
+    }
+
+    fn children(&self) -> &[(String, Vdf)] {
+        match self {
+            Vdf::Map(entries) => entries,
+            Vdf::Str(_) => &[],
+        }
+    }
+}
+
+// Installed games from every library, launched through the Steam client
+pub fn scan(settings: &SteamSettings) -> Vec<AppEntry> {
+    let root = match &settings.root {
+        Some(root) => PathBuf::from(shellexpand::tilde(root).as_ref()),
+        None => match ROOTS
+            .iter()
+            .map(|root| PathBuf::from(shellexpand::tilde(root).as_ref()))
+            .find(|root| root.join("steamapps").is_dir())
+        {
+            Some(root) => root,
+            None => return Vec::new(),
+        },
+    };
+
+    let mut apps = Vec::new();
+    for library in libraries(&root) {
+        let wanted_library = settings.libraries.is_empty()
+            || settings
+                .libraries
+                .iter()
+                .any(|l| Path::new(shellexpand::tilde(l).as_ref()) == library);
+        if !wanted_library {
+            continue;
+        }
+
+        for (appid, name) in installed_games(&library) {
+            let listed = |list: &[String]| list.iter().any(|g| *g == appid || *g == name);
+            let is_tool = TOOLS.iter().any(|tool| name.starts_with(tool));
+
+            if ((settings.games.is_empty() && !is_tool) || listed(&settings.games))
+                && !listed(&settings.exclude)
+            {
+                apps.push(entry(settings, &root, &appid, name));
+            }
+        }
+    }
+
+    apps.sort_by_key(|entry| entry.name.to_lowercase());
+    apps
+}
+
+// The steam:// url only asks a running client to start the game and exits straight away,
+// so the tile can't track the game. The default hooks are turned off since post_exit would
+// run while the game is still starting
+fn entry(settings: &SteamSettings, root: &Path, appid: &str, name: String) -> AppEntry {
+    let (program, mut args) = settings
+        .launcher
+        .split_first()
+        .map(|(program, args)| (program.clone(), args.to_vec()))
+        .unwrap_or_else(|| ("steam".to_string(), Vec::new()));
+
+    args.push(format!("steam://rungameid/{}", appid));
+
+    AppEntry {
+        name,
+        run: None,
+        command: Some(LaunchCommand {
+            program,
+            args,
+            cwd: None,
+            env: BTreeMap::new(),
+            clear_env: false,
+        }),
+        icon: artwork(root, appid)
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        // The freedesktop main category, so games from .desktop files share the shelf
+        category: Some("Game".to_string()),
+        pre_launch: Some(String::new()),
+        post_exit: Some(String::new()),
+    }
+}
+
+// The root's own steamapps is always a library, libraryfolders.vdf lists any others
+fn libraries(root: &Path) -> Vec<PathBuf> {
+    let mut libraries = vec![root.to_path_buf()];
+
+    let Some(folders) = fs::read_to_string(root.join("steamapps/libraryfolders.vdf"))
+        .ok()
+        .and_then(|file| parse(&file))
+    else {
+        return libraries;
+    };
+
+    for (_, folder) in folders.get("libraryfolders").map_or(&[][..], Vdf::children) {
+        if let Some(path) = folder.str("path").map(PathBuf::from)
+            && !libraries.contains(&path)
+        {
+            libraries.push(path);
+        }
+    }
+
+    libraries
+}
+
+// Appid and name of each fully installed game in a library
+fn installed_games(library: &Path) -> Vec<(String, String)> {
+    let Ok(dir) = fs::read_dir(library.join("steamapps")) else {
+        return Vec::new();
+    };
+
+    let mut games = Vec::new();
+    for file in dir.flatten() {
+        let file_name = file.file_name();
+        let file_name = file_name.to_string_lossy();
+        if !file_name.starts_with("appmanifest_") || !file_name.ends_with(".acf") {
+            continue;
+        }
+
+        let Some(manifest) = fs::read_to_string(file.path())
+            .ok()
+            .and_then(|file| parse(&file))
+        else {
+            continue;
+        };
+        let Some(state) = manifest.get("AppState") else {
+            continue;
+        };
+
+        let installed = state
+            .str("StateFlags")
+            .and_then(|flags| flags.parse::<u32>().ok())
+            .is_some_and(|flags| flags & FULLY_INSTALLED != 0);
+        if let (Some(appid), Some(name), true) = (state.str("appid"), state.str("name"), installed)
+        {
+            games.push((appid.to_string(), name.to_string()));
+        }
+    }
+
+    games
+}
+
+// Older clients keep <appid>_<art> in librarycache, newer ones a folder per app,
+// sometimes with the files a level further down
+fn artwork(root: &Path, appid: &str) -> Option<PathBuf> {
+    let cache = root.join("appcache/librarycache");
+
+    for art in ART {
+        let candidates = [
+            cache.join(format!("{}_{}", appid, art)),
+            cache.join(appid).join(art),
+        ];
+        if let Some(path) = candidates.into_iter().find(|path| path.is_file()) {
+            return Some(path);
+        }
+
+        let nested = fs::read_dir(cache.join(appid))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path().join(art))
+            .find(|path| path.is_file());
+        if nested.is_some() {
+            return nested;
+        }
+    }
+
+    None
+}
+
+// KeyValues text: quoted or bare tokens, braces for nesting, // comments
+fn parse(text: &str) -> Option<Vdf> {
+    let tokens = tokenize(text);
+    let mut pos = 0;
+    parse_map(&tokens, &mut pos, false)
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Str(String),
+}
+
+fn parse_map(tokens: &[Token], pos: &mut usize, nested: bool) -> Option<Vdf> {
+    let mut entries = Vec::new();
+
+    while let Some(token) = tokens.get(*pos) {
+        *pos += 1;
+        let key = match token {
+            Token::Str(key) => key.clone(),
+            Token::Close if nested => return Some(Vdf::Map(entries)),
+            _ => return None,
+        };
+
+        let value = match tokens.get(*pos)? {
+            Token::Open => {
+                *pos += 1;
+                parse_map(tokens, pos, true)?
+            }
+            Token::Str(value) => {
+                *pos += 1;
+                Vdf::Str(value.clone())
+            }
+            Token::Close => return None,
+        };
+        entries.push((key, value));
+    }
+
+    // Running out of tokens is only fine at the top level
+    (!nested).then_some(Vdf::Map(entries))
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '"' => {
+                let mut s = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some(other) => s.push(other),
+                            None => {}
+                        },
+                        _ => s.push(c),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut s = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '{' || next == '}' || next == '"' {
+                        break;
+                    }
+                    s.push(next);
+                    chars.next();
+                }
+                tokens.push(Token::Str(s));
+            }
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A Steam root under the temp dir, removed again when dropped
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("htpc_steam_{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(root.join("steamapps")).unwrap();
+            Self(root)
+        }
+
+        fn write(&self, file: &str, contents: &str) {
+            let path = self.0.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn manifest(appid: &str, name: &str, flags: u32) -> String {
+        format!(
+            "\"AppState\"\n{{\n\t\"appid\"\t\t\"{}\"\n\t\"name\"\t\t\"{}\"\n\t\"StateFlags\"\t\t\"{}\"\n}}\n",
+            appid, name, flags
+        )
+    }
+
+    #[test]
+    fn parses_nested_maps_and_comments() {
+        let vdf = parse(
+            r#"
+            // written by Steam
+            "libraryfolders"
+            {
+                "0"
+                {
+                    "path"    "/home/me/.local/share/Steam"
+                    "apps" { "228980" "123" }
+                }
+                "1" { "path" "/mnt/games\\Steam Library" }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let folders = vdf.get("LibraryFolders").unwrap().children();
+        assert_eq!(folders.len(), 2);
+        assert_eq!(
+            folders[0].1.str("path"),
+            Some("/home/me/.local/share/Steam")
+        );
+        assert_eq!(folders[0].1.get("apps").unwrap().str("228980"), Some("123"));
+        assert_eq!(folders[1].1.str("path"), Some("/mnt/games\\Steam Library"));
+    }
+
+    #[test]
+    fn rejects_unbalanced_braces() {
+        assert!(parse(r#""a" { "b" "c""#).is_none());
+        assert!(parse(r#""a" "b" }"#).is_none());
+    }
+
+    #[test]
+    fn lists_every_library_once() {
+        let steam = Fixture::new("libraries");
+        let other = steam.0.join("other");
+        steam.write(
+            "steamapps/libraryfolders.vdf",
+            &format!(
+                "\"libraryfolders\" {{ \"0\" {{ \"path\" \"{}\" }} \"1\" {{ \"path\" \"{}\" }} }}",
+                steam.0.display(),
+                other.display()
+            ),
+        );
+
+        assert_eq!(libraries(&steam.0), vec![steam.0.clone(), other]);
+    }
+
+    #[test]
+    fn skips_games_that_are_not_fully_installed() {
+        let steam = Fixture::new("installed");
+        steam.write("steamapps/appmanifest_10.acf", &manifest("10", "Done", 4));
+        steam.write(
+            "steamapps/appmanifest_20.acf",
+            &manifest("20", "Updating", 1030),
+        );
+        steam.write(
+            "steamapps/appmanifest_30.acf",
+            &manifest("30", "Downloading", 1026),
+        );
+        steam.write("steamapps/notes.acf", &manifest("40", "Not a manifest", 4));
+
+        let mut games = installed_games(&steam.0);
+        games.sort();
+        assert_eq!(
+            games,
+            vec![
+                ("10".to_string(), "Done".to_string()),
+                ("20".to_string(), "Updating".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_artwork_in_old_and_new_layouts() {
+        let steam = Fixture::new("artwork");
+        steam.write("appcache/librarycache/10_header.jpg", "");
+        steam.write("appcache/librarycache/10/library_600x900.jpg", "");
+        steam.write("appcache/librarycache/20/abc123/header.jpg", "");
+
+        let cache = steam.0.join("appcache/librarycache");
+        assert_eq!(
+            artwork(&steam.0, "10"),
+            Some(cache.join("10/library_600x900.jpg"))
+        );
+        assert_eq!(
+            artwork(&steam.0, "20"),
+            Some(cache.join("20/abc123/header.jpg"))
+        );
+        assert_eq!(artwork(&steam.0, "30"), None);
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fs,
+    io::{self, BufRead, BufReader},
+    os::unix::process::CommandExt,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+// Lines of stderr kept per child
+const STDERR_TAIL: usize = 12;
+
+// How long a reaped child's stderr gets to drain, a process it left behind may hold the pipe open
+const STDERR_DRAIN: Duration = Duration::from_millis(200);
+
+// Process groups still alive this long after SIGTERM get SIGKILL
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
+
+// A child process started by the launcher
+pub struct Supervised {
+    pub name: String,
+    pub pid: u32,
+    pub started: Instant,
+    pub paused: bool,
+    // Last time the app was launched or brought up, the newest is in the foreground
+    raised: Instant,
+    child: Child,
+    stderr: Arc<Mutex<VecDeque<String>>>,
+    stderr_reader: Option<JoinHandle<()>>,
+}
+
+// A child that has been reaped
+pub struct Exited {
+    pub name: String,
+    pub pid: u32,
+    pub started: Instant,
+    pub status: ExitStatus,
+    pub stderr_tail: Vec<String>,
+}
+
+// Owns every app launched from the grid so their state can be tracked
+#[derive(Default)]
+pub struct Supervisor {
+    children: Vec<Supervised>,
+    // Reaped children whose stderr is still draining, and when to stop waiting for it
+    exiting: Vec<(Supervised, ExitStatus, Instant)>,
+    // Groups sent SIGTERM, tracked apart from children since the leader may exit first
+    pending_kills: Vec<(u32, Instant)>,
+    // Window raises running in the background, each returns whether a window came up
+    raising: Vec<(String, JoinHandle<bool>)>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Spawns the command in its own process group and tracks it under name
+    pub fn spawn(&mut self, name: &str, mut cmd: Command) -> Result<u32, Box<dyn Error>> {
+        cmd.process_group(0);
+        cmd.stderr(Stdio::piped());
+        let mut child = cmd.spawn()?;
+        let pid = child.id();
+
+        // Forward stderr to our own while keeping the last few lines
+        let stderr = Arc::new(Mutex::new(VecDeque::new()));
+        let stderr_reader = child.stderr.take().map(|pipe| {
+            let tail = Arc::clone(&stderr);
+            let name = name.to_string();
+            thread::spawn(move || {
+                for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+                    eprintln!("[{}] {}", name, line);
+                    let mut tail = tail.lock().unwrap();
+                    if tail.len() == STDERR_TAIL {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+            })
+        });
+
+        self.children.push(Supervised {
+            name: name.to_string(),
+            pid,
+            started: Instant::now(),
+            paused: false,
+            raised: Instant::now(),
+            child,
+            stderr,
+            stderr_reader,
+        });
+
+        Ok(pid)
+    }
+
+    // Reaps finished children and returns them
+    pub fn poll(&mut self) -> Vec<Exited> {
+        let mut exited = Vec::new();
+
+        self.pending_kills.retain(|(pgid, deadline)| {
+            if Instant::now() < *deadline {
+                return true;
+            }
+            // ESRCH means the group already exited
+            if signal_group(*pgid, libc::SIGKILL).is_ok() {
+                println!("Process group {} ignored SIGTERM, killed", pgid);
+            }
+            false
+        });
+
+        let mut i = 0;
+        while i < self.children.len() {
+            let sup = &mut self.children[i];
+            match sup.child.try_wait() {
+                Ok(Some(status)) => {
+                    let sup = self.children.remove(i);
+                    self.exiting
+                        .push((sup, status, Instant::now() + STDERR_DRAIN));
+                }
+                Ok(None) => i += 1,
+                Err(e) => {
+                    eprintln!("Failed to wait on {} ({}): {}", sup.name, sup.pid, e);
+                    i += 1;
+                }
+            }
+        }
+
+        // The last lines usually say why it failed, they are reported once the reader has
+        // caught up to them
+        self.exiting.retain(|(sup, status, deadline)| {
+            let drained = sup
+                .stderr_reader
+                .as_ref()
+                .is_none_or(|reader| reader.is_finished());
+            if !drained && Instant::now() < *deadline {
+                return true;
+            }
+            exited.push(Exited {
+                name: sup.name.clone(),
+                pid: sup.pid,
+                started: sup.started,
+                status: *status,
+                stderr_tail: sup.stderr.lock().unwrap().iter().cloned().collect(),
+            });
+            false
+        });
+
+        exited
+    }
+
+    pub fn is_running(&self, name: &str) -> bool {
+        self.children.iter().any(|sup| sup.name == name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Supervised> {
+        self.children.iter().find(|sup| sup.name == name)
+    }
+
+    pub fn running(&self) -> impl Iterator<Item = &Supervised> {
+        self.children.iter()
+    }
+
+    // The app most recently launched or brought up
+    pub fn foreground(&self) -> Option<&Supervised> {
+        self.children.iter().max_by_key(|sup| sup.raised)
+    }
+
+    // Stops the whole process group until it is resumed
+    pub fn pause(&mut self, name: &str) -> io::Result<()> {
+        let Some(sup) = self.children.iter_mut().find(|sup| sup.name == name) else {
+            return Ok(());
+        };
+        signal_group(sup.pid, libc::SIGSTOP)?;
+        sup.paused = true;
+        Ok(())
+    }
+
+    pub fn resume(&mut self, name: &str) -> io::Result<()> {
+        let Some(sup) = self.children.iter_mut().find(|sup| sup.name == name) else {
+            return Ok(());
+        };
+        signal_group(sup.pid, libc::SIGCONT)?;
+        sup.paused = false;
+        Ok(())
+    }
+
+    // Asks the whole process group to exit, killing it if it is still there after a timeout
+    pub fn terminate(&mut self, name: &str) -> io::Result<()> {
+        let Some(sup) = self.get(name) else {
+            return Ok(());
+        };
+        let pid = sup.pid;
+        signal_group(pid, libc::SIGTERM)?;
+        // A stopped group only sees SIGTERM once it is continued
+        if sup.paused {
+            self.resume(name)?;
+        }
+
+        if !self.pending_kills.iter().any(|(pgid, _)| *pgid == pid) {
+            self.pending_kills
+                .push((pid, Instant::now() + KILL_TIMEOUT));
+        }
+        Ok(())
+    }
+
+    // Resumes the app if paused and raises a window belonging to its process group with
+    // xdotool on a worker thread, the outcome is picked up by raised()
+    pub fn focus(&mut self, name: &str) {
+        if self.get(name).is_some_and(|sup| sup.paused)
+            && let Err(e) = self.resume(name)
+        {
+            eprintln!("Failed to resume {}: {}", name, e);
+        }
+
+        let Some(sup) = self.children.iter_mut().find(|sup| sup.name == name) else {
+            return;
+        };
+        sup.raised = Instant::now();
+
+        let pgid = sup.pid;
+        let raise = thread::spawn(move || {
+            group_pids(pgid).into_iter().any(|pid| {
+                Command::new("xdotool")
+                    .args([
+                        "search",
+                        "--onlyvisible",
+                        "--pid",
+                        &pid.to_string(),
+                        "windowactivate",
+                    ])
+                    .status()
+                    .map(|status| status.success())
+                    .unwrap_or(false)
+            })
+        });
+        self.raising.push((name.to_string(), raise));
+    }
+
+    // Finished raises, false if no window was found or xdotool isn't there
+    pub fn raised(&mut self) -> Vec<(String, bool)> {
+        let mut raised = Vec::new();
+        let mut i = 0;
+        while i < self.raising.len() {
+            if !self.raising[i].1.is_finished() {
+                i += 1;
+                continue;
+            }
+            let (name, raise) = self.raising.remove(i);
+            raised.push((name, raise.join().unwrap_or(false)));
+        }
+        raised
+    }
+}
+
+fn signal_group(pgid: u32, signal: libc::c_int) -> io::Result<()> {
+    // A negative pid signals every process in the group
+    if unsafe { libc::kill(-(pgid as libc::pid_t), signal) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// All live pids in a process group, scanned from /proc
+pub fn group_pids(pgid: u32) -> Vec<u32> {
+    let mut pids = vec![pgid];
+
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return pids;
+    };
+
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if pid == pgid {
+            continue;
+        }
+
+        let Ok(stat) = fs::read_to_string(entry.path().join("stat")) else {
+            continue;
+        };
+
+        // Fields after the parenthesised command name: state ppid pgrp ...
+        let Some(rest) = stat.rfind(')').map(|i| &stat[i + 1..]) else {
+            continue;
+        };
+        if rest
+            .split_whitespace()
+            .nth(2)
+            .and_then(|s| s.parse::<u32>().ok())
+            == Some(pgid)
+        {
+            pids.push(pid);
+        }
+    }
+
+    pids
+}
@@ -0,0 +1,228 @@
+use eframe::egui;
+use std::{
+    collections::HashMap,
+    fs,
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, Sender, channel},
+    },
+    thread,
+    time::SystemTime,
+};
+
+// Threads decoding images off the UI thread
+const DECODE_WORKERS: usize = 2;
+
+// A downsampled image is decoded again once it is asked for this much bigger
+const UPSCALE_LIMIT: f32 = 1.25;
+
+pub enum Slot {
+    Loading,
+    Ready(egui::TextureHandle),
+    Failed,
+}
+
+enum State {
+    Loading,
+    // None when the file couldn't be decoded, so it isn't retried every frame
+    Done(Option<egui::TextureHandle>),
+}
+
+struct Cached {
+    modified: Option<SystemTime>,
+    max_size: [usize; 2], // What the latest decode was scaled to fit
+    downsampled: bool,    // false once the image is at its own resolution
+    state: State,
+}
+
+struct Job {
+    path: String,
+    modified: Option<SystemTime>,
+    max_size: [usize; 2],
+}
+
+struct Decoded {
+    path: String,
+    modified: Option<SystemTime>,
+    max_size: [usize; 2],
+    image: Result<(egui::ColorImage, bool), String>,
+}
+
+// Keeps textures across frames, decoding on worker threads and reloading only when the file changes
+pub struct TextureCache {
+    entries: HashMap<String, Cached>,
+    jobs: Sender<Job>,
+    results: Receiver<Decoded>,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl TextureCache {
+    pub fn new(ctx: &egui::Context) -> Self {
+        let (jobs, job_rx) = channel::<Job>();
+        let (result_tx, results) = channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        for _ in 0..DECODE_WORKERS {
+            let job_rx = Arc::clone(&job_rx);
+            let result_tx = result_tx.clone();
+            let ctx = ctx.clone();
+
+            thread::spawn(move || {
+                loop {
+                    let Ok(job) = job_rx.lock().unwrap().recv() else {
+                        return;
+                    };
+                    let image = decode_image(&job.path, job.max_size);
+                    let decoded = Decoded {
+                        path: job.path,
+                        modified: job.modified,
+                        max_size: job.max_size,
+                        image,
+                    };
+                    if result_tx.send(decoded).is_err() {
+                        return;
+                    }
+                    ctx.request_repaint();
+                }
+            });
+        }
+
+        Self {
+            entries: HashMap::new(),
+            jobs,
+            results,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    // Uploads images the workers have finished, returning the paths that failed to decode
+    pub fn collect(&mut self, ctx: &egui::Context) -> Vec<(String, String)> {
+        let mut failed = Vec::new();
+        while let Ok(decoded) = self.results.try_recv() {
+            // Results for a file that changed again or was asked for bigger while
+            // decoding are stale
+            let Some(cached) = self.entries.get_mut(&decoded.path) else {
+                continue;
+            };
+            if cached.modified != decoded.modified || cached.max_size != decoded.max_size {
+                continue;
+            }
+
+            let texture = match decoded.image {
+                Ok((image, downsampled)) => {
+                    cached.downsampled = downsampled;
+                    Some(ctx.load_texture(&decoded.path, image, Default::default()))
+                }
+                // Missing files are left to the config checks, no icon or background is fine
+                Err(e) => {
+                    if std::path::Path::new(&decoded.path).is_file() {
+                        failed.push((decoded.path, e));
+                    }
+                    None
+                }
+            };
+            cached.state = State::Done(texture);
+        }
+        failed
+    }
+
+    // Looks up a texture, queueing a decode scaled to fit max_size if it isn't cached
+    // or was cached smaller, the smaller one is used until the new one is ready
+    pub fn get(&mut self, path: &str, max_size: egui::Vec2) -> Slot {
+        let path = shellexpand::tilde(path).to_string();
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        let max_size = [max_size.x as usize, max_size.y as usize];
+
+        if let Some(cached) = self.entries.get_mut(&path)
+            && cached.modified == modified
+        {
+            let too_small = cached.downsampled
+                && (0..2).any(|i| max_size[i] as f32 > cached.max_size[i] as f32 * UPSCALE_LIMIT);
+            if too_small
+                && self
+                    .jobs
+                    .send(Job {
+                        path: path.clone(),
+                        modified,
+                        max_size,
+                    })
+                    .is_ok()
+            {
+                cached.max_size = max_size;
+                cached.downsampled = false;
+            }
+
+            self.hits += 1;
+            return match &cached.state {
+                State::Loading => Slot::Loading,
+                State::Done(Some(texture)) => Slot::Ready(texture.clone()),
+                State::Done(None) => Slot::Failed,
+            };
+        }
+
+        self.misses += 1;
+        let job = Job {
+            path: path.clone(),
+            modified,
+            max_size,
+        };
+        if self.jobs.send(job).is_err() {
+            return Slot::Failed;
+        }
+
+        self.entries.insert(
+            path,
+            Cached {
+                modified,
+                max_size,
+                downsampled: false,
+                state: State::Loading,
+            },
+        );
+        Slot::Loading
+    }
+
+    // Drops textures for paths that are no longer used, and failed ones so they are
+    // tried and reported again
+    pub fn retain(&mut self, paths: &[String]) {
+        let paths: Vec<String> = paths
+            .iter()
+            .map(|path| shellexpand::tilde(path).to_string())
+            .collect();
+        self.entries.retain(|path, cached| {
+            paths.contains(path) && !matches!(cached.state, State::Done(None))
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.entries
+            .values()
+            .filter(|cached| matches!(cached.state, State::Loading))
+            .count()
+    }
+}
+
+// Load texture from file, downsampled so it is no larger than max_size, and whether it was
+fn decode_image(path: &str, max_size: [usize; 2]) -> Result<(egui::ColorImage, bool), String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    let mut image = image::load_from_memory(&data).map_err(|e| e.to_string())?;
+
+    let [max_w, max_h] = max_size.map(|n| n.max(1) as u32);
+    let downsampled = image.width() > max_w || image.height() > max_h;
+    if downsampled {
+        image = image.thumbnail(max_w, max_h);
+    }
+
+    let image = image.to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Ok((
+        egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw()),
+        downsampled,
+    ))
+}
@@ -0,0 +1,132 @@
+use eframe::egui;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// How long informational notices stay on screen
+const NOTICE_TIME: Duration = Duration::from_secs(4);
+
+pub struct Toast {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+// Error messages drawn over the grid until dismissed, and short notices that fade on their own
+#[derive(Default)]
+pub struct Toasts {
+    queue: VecDeque<Toast>,
+    notices: Vec<(String, Instant)>,
+}
+
+impl Toasts {
+    pub fn notify(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        println!("{}", text);
+        self.notices.push((text, Instant::now()));
+    }
+
+    pub fn push(&mut self, title: impl Into<String>, lines: Vec<String>) {
+        let title = title.into();
+        eprintln!("{}", title);
+        for line in &lines {
+            eprintln!("  {}", line);
+        }
+
+        self.queue.push_back(Toast { title, lines });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn dismiss(&mut self) {
+        self.queue.pop_front();
+    }
+
+    // Draws the oldest toast, the rest wait behind it
+    pub fn draw(&mut self, ctx: &egui::Context) {
+        self.draw_notices(ctx);
+
+        let Some(toast) = self.queue.front() else {
+            return;
+        };
+
+        let screen_rect = ctx.screen_rect();
+
+        egui::Area::new("toast")
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -60.0))
+            .show(ctx, |ui| {
+                egui::Frame::none()
+                    .fill(egui::Color32::from_rgba_unmultiplied(60, 10, 10, 230))
+                    .stroke(egui::Stroke::new(2.0, egui::Color32::from_rgb(220, 80, 80)))
+                    .rounding(12.0)
+                    .inner_margin(24.0)
+                    .show(ui, |ui| {
+                        ui.set_max_width(screen_rect.width() * 0.6);
+
+                        ui.label(
+                            egui::RichText::new(&toast.title)
+                                .size(36.0)
+                                .color(egui::Color32::WHITE),
+                        );
+
+                        for line in &toast.lines {
+                            ui.label(
+                                egui::RichText::new(line)
+                                    .monospace()
+                                    .size(18.0)
+                                    .color(egui::Color32::LIGHT_GRAY),
+                            );
+                        }
+
+                        ui.add_space(12.0);
+
+                        let more = if self.queue.len() > 1 {
+                            format!("  ({} more)", self.queue.len() - 1)
+                        } else {
+                            String::new()
+                        };
+                        ui.label(
+                            egui::RichText::new(format!("Press A / Enter to dismiss{}", more))
+                                .size(20.0)
+                                .color(egui::Color32::GRAY),
+                        );
+                    });
+            });
+    }
+
+    // Notices stack in the top left corner
+    fn draw_notices(&mut self, ctx: &egui::Context) {
+        self.notices
+            .retain(|(_, shown)| shown.elapsed() < NOTICE_TIME);
+
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            "notice_layer".into(),
+        ));
+        let mut pos = ctx.screen_rect().left_top() + egui::vec2(30.0, 30.0);
+
+        for (text, shown) in &self.notices {
+            // Fade out over the last second
+            let left = NOTICE_TIME.saturating_sub(shown.elapsed()).as_secs_f32();
+            let alpha = left.clamp(0.0, 1.0);
+
+            let galley = painter.layout_no_wrap(
+                text.clone(),
+                egui::FontId::proportional(28.0),
+                egui::Color32::WHITE.linear_multiply(alpha),
+            );
+            let rect = egui::Rect::from_min_size(pos, galley.size()).expand(12.0);
+            painter.rect_filled(
+                rect,
+                10.0,
+                egui::Color32::from_black_alpha((200.0 * alpha) as u8),
+            );
+            painter.galley(pos, galley);
+
+            pos.y += rect.height() + 10.0;
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, channel},
+};
+
+// Watches the config directory so edits show up without a restart
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+    rx: Receiver<notify::Result<Event>>,
+}
+
+impl ConfigWatcher {
+    pub fn new(dir: &Path) -> notify::Result<Self> {
+        let (tx, rx) = channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            rx,
+        })
+    }
+
+    // Paths touched since the last call
+    pub fn changed(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+
+        while let Ok(event) = self.rx.try_recv() {
+            match event {
+                Ok(event) if !event.kind.is_access() => paths.extend(event.paths),
+                Ok(_) => {}
+                Err(e) => eprintln!("Config watcher error: {}", e),
+            }
+        }
+
+        paths
+    }
+}
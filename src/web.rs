@@ -0,0 +1,178 @@
+use crate::{
+    config::{self, RemoteSettings},
+    ipc::{Command, Remote},
+};
+use std::{
+    error::Error,
+    fs::{self, OpenOptions},
+    io::{Read, Write},
+    net::{IpAddr, UdpSocket},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+    thread,
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const PAGE: &str = include_str!("remote.html");
+
+// Serves the phone remote, returning the pairing url to show on the TV
+pub fn start(settings: &RemoteSettings, remote: Remote) -> Result<String, Box<dyn Error>> {
+    let token = match &settings.token {
+        Some(token) => token.clone(),
+        None => load_token()?,
+    };
+
+    let server = Server::http(&settings.bind).map_err(|e| format!("{}: {}", settings.bind, e))?;
+    let addr = server
+        .server_addr()
+        .to_ip()
+        .ok_or("not listening on an ip address")?;
+
+    let host = if addr.ip().is_unspecified() {
+        lan_ip().unwrap_or(addr.ip())
+    } else {
+        addr.ip()
+    };
+    let url = format!("http://{}:{}/?token={}", host, addr.port(), token);
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let remote = remote.clone();
+            let token = token.clone();
+            thread::spawn(move || respond(request, &remote, &token));
+        }
+    });
+
+    Ok(url)
+}
+
+fn respond(request: Request, remote: &Remote, token: &str) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    // The page itself holds nothing secret, it reads the token from its own url
+    if request.method() == &Method::Get && path == "/" {
+        let _ = request.respond(with_type(Response::from_string(PAGE), "text/html"));
+        return;
+    }
+
+    if !authorized(&request, query, token) {
+        let _ =
+            request.respond(Response::from_string("bad or missing token").with_status_code(401));
+        return;
+    }
+
+    let result = match (request.method(), parts.as_slice()) {
+        (Method::Get, ["api", "apps"]) => remote.call(Command::List),
+        (Method::Get, ["api", "status"]) => remote.call(Command::Status),
+        (Method::Get, ["api", "icon", idx]) => match app(remote, idx).and_then(|app| icon(&app)) {
+            Ok(response) => {
+                let _ = request.respond(response);
+                return;
+            }
+            Err(e) => Err(e),
+        },
+        (Method::Post, ["api", "launch", idx]) => app(remote, idx).and_then(|app| {
+            let name = app["name"].as_str().unwrap_or_default().to_string();
+            remote.call(Command::Launch(name))
+        }),
+        (Method::Post, ["api", "press", action]) => {
+            Command::parse(&format!("press {}", action)).and_then(|command| remote.call(command))
+        }
+        (Method::Post, ["api", "home"]) => remote.call(Command::Home),
+        _ => {
+            let _ = request.respond(Response::from_string("not found").with_status_code(404));
+            return;
+        }
+    };
+
+    let response = match result {
+        Ok(body) if body.is_empty() => with_type(Response::from_string("{}"), "application/json"),
+        Ok(body) => with_type(Response::from_string(body), "application/json"),
+        Err(e) => Response::from_string(e).with_status_code(400),
+    };
+    let _ = request.respond(response);
+}
+
+// The token comes in a header from the page's fetches, or in the query for icon urls
+fn authorized(request: &Request, query: &str, token: &str) -> bool {
+    let header = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("X-Token"))
+        .map(|header| header.value.as_str());
+    let param = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("token="));
+
+    header.or(param).is_some_and(|given| given == token)
+}
+
+// An entry of the list reply by index, looked up fresh so reloads are seen
+fn app(remote: &Remote, idx: &str) -> Result<serde_json::Value, String> {
+    let idx: usize = idx.parse().map_err(|_| format!("bad index '{}'", idx))?;
+    let list: serde_json::Value =
+        serde_json::from_str(&remote.call(Command::List)?).map_err(|e| e.to_string())?;
+
+    list.get(idx)
+        .cloned()
+        .ok_or_else(|| format!("no app at index {}", idx))
+}
+
+fn icon(app: &serde_json::Value) -> Result<Response<fs::File>, String> {
+    let path = app["icon"].as_str().unwrap_or_default();
+    let file = fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+
+    let mime = match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    };
+    Ok(with_type(Response::from_file(file), mime))
+}
+
+fn with_type<R: Read>(response: Response<R>, mime: &str) -> Response<R> {
+    match Header::from_bytes("Content-Type", mime) {
+        Ok(header) => response.with_header(header),
+        Err(()) => response,
+    }
+}
+
+// Kept in the config directory so a paired phone stays paired across restarts
+fn load_token() -> Result<String, Box<dyn Error>> {
+    let path = config::config_path("remote_token");
+    match fs::read_to_string(&path) {
+        Ok(token) if !token.trim().is_empty() => return Ok(token.trim().to_string()),
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("{}: {}", path, e).into()),
+    }
+
+    let mut bytes = [0u8; 16];
+    fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    if let Some(dir) = Path::new(&path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .mode(0o600)
+        .open(&path)?
+        .write_all(format!("{}\n", token).as_bytes())?;
+
+    println!("Generated a new phone remote token in {}", path);
+    Ok(token)
+}
+
+// The address other machines on the LAN reach us on, connecting a UDP socket sends nothing
+fn lan_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}